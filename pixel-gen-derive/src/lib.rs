@@ -0,0 +1,145 @@
+//! Derives for pixel-gen's animation traits, so materials and their configs
+//! don't have to forward every method by hand.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Member};
+
+/// Implements `AnimatedMaterialConfig` by delegating to the field marked
+/// `#[animation]`, usually `Tracks`.
+///
+/// ```ignore
+/// #[derive(Component, AnimatedMaterialConfig)]
+/// pub struct NebulaeConfig {
+///     #[animation]
+///     tracks: Tracks,
+/// }
+/// ```
+#[proc_macro_derive(AnimatedMaterialConfig, attributes(animation))]
+pub fn derive_animated_material_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_config(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `AnimatedMaterial2D` with a track for every uniform marked
+/// `#[animate]`, named after its field. `f32` uniforms are animated whole and
+/// vectors a component at a time, as `field.x`.
+///
+/// ```ignore
+/// #[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+/// pub struct PlanetsMaterial {
+///     #[uniform(0)]
+///     #[animate]
+///     size: f32,
+///     #[uniform(1)]
+///     #[animate]
+///     light_origin: Vec2,
+/// }
+/// ```
+#[proc_macro_derive(AnimatedMaterial2D, attributes(animate))]
+pub fn derive_animated_material_2d(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_material(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_config(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut fields = marked(input, "animation")?.into_iter();
+    let Some((member, _)) = fields.next() else {
+        return Err(Error::new_spanned(input, "mark one field #[animation]"));
+    };
+    if let Some((extra, _)) = fields.next() {
+        return Err(Error::new(
+            extra.span(),
+            "only one field can be marked #[animation]",
+        ));
+    }
+
+    Ok(implement(
+        input,
+        quote!(::pixel_gen::AnimatedMaterialConfig),
+        quote! {
+            fn advance(&mut self, delta: f32) {
+                ::pixel_gen::AnimatedMaterialConfig::advance(&mut self.#member, delta)
+            }
+
+            fn values(&self) -> Vec<(&str, f32)> {
+                ::pixel_gen::AnimatedMaterialConfig::values(&self.#member)
+            }
+
+            fn finished(&self) -> bool {
+                ::pixel_gen::AnimatedMaterialConfig::finished(&self.#member)
+            }
+        },
+    ))
+}
+
+fn expand_material(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = marked(input, "animate")?;
+    if fields.is_empty() {
+        return Err(Error::new_spanned(
+            input,
+            "mark the uniforms to animate with #[animate]",
+        ));
+    }
+    let (members, names): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+
+    Ok(implement(
+        input,
+        quote!(::pixel_gen::AnimatedMaterial2D),
+        quote! {
+            fn get(&self, track: &str) -> Option<f32> {
+                let (field, component) = ::pixel_gen::split_track(track);
+                match field {
+                    #(#names => ::pixel_gen::AnimatedUniform::component(&self.#members, component),)*
+                    _ => None,
+                }
+            }
+
+            fn update(&mut self, track: &str, value: f32) {
+                let (field, component) = ::pixel_gen::split_track(track);
+                match field {
+                    #(#names => ::pixel_gen::AnimatedUniform::set_component(
+                        &mut self.#members,
+                        component,
+                        value,
+                    ),)*
+                    _ => {}
+                }
+            }
+        },
+    ))
+}
+
+/// Every field marked `#[attribute]` and the name its track goes by.
+fn marked(input: &DeriveInput, attribute: &str) -> syn::Result<Vec<(Member, String)>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(input, "can only be derived for structs"));
+    };
+
+    Ok(data
+        .fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.attrs.iter().any(|a| a.path().is_ident(attribute)))
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+            None => (Member::Unnamed(index.into()), index.to_string()),
+        })
+        .collect())
+}
+
+fn implement(input: &DeriveInput, r#trait: TokenStream2, body: TokenStream2) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #r#trait for #name #ty_generics #where_clause {
+            #body
+        }
+    }
+}
@@ -0,0 +1,79 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+layout(set = 2, binding = 0) uniform float pixels;
+layout(set = 2, binding = 1) uniform float seed;
+layout(set = 2, binding = 2) uniform vec2 light_origin;
+
+layout(set = 2, binding = 3) uniform texture2D texture_;
+layout(set = 2, binding = 4) uniform sampler sampler_;
+
+float rand(vec2 coord) {
+	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * 43758.5453);
+}
+
+float noise(vec2 coord){
+	vec2 i = floor(coord);
+	vec2 f = fract(coord);
+
+	float a = rand(i);
+	float b = rand(i + vec2(1.0, 0.0));
+	float c = rand(i + vec2(0.0, 1.0));
+	float d = rand(i + vec2(1.0, 1.0));
+
+	vec2 cubic = f * f * (3.0 - 2.0 * f);
+
+	return mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y;
+}
+
+float fbm(vec2 coord){
+	float value = 0.0;
+	float scale = 0.5;
+
+	for(int i = 0; i < 3 ; i++){
+		value += noise(coord) * scale;
+		coord *= 2.0;
+		scale *= 0.5;
+	}
+	return value;
+}
+
+bool dither(vec2 uv1, vec2 uv2) {
+	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
+}
+
+void main() {
+	// pixelizing and dithering
+	vec2 uv = floor(UV * pixels) / pixels;
+	bool dith = dither(UV, uv);
+
+	// a lumpy outline, from noise sampled around a circle so it wraps
+	vec2 p = (uv - 0.5) * 2.0;
+	float d = length(p);
+	float angle = atan(p.y, p.x);
+	float edge = 0.6 + fbm(vec2(cos(angle), sin(angle)) * 1.5 + seed) * 0.45;
+
+	// shading from the light, darkest colors left for craters and the rim
+	float light = clamp(1.0 - distance(uv, light_origin) * 1.2, 0.0, 1.0);
+	if (dith) {
+		light *= 0.9;
+	}
+	float k = 1.0 + floor(light * 3.99);
+
+	if (fbm(uv * 4.0 + seed * 2.0) < 0.3) {
+		k -= 1.0;
+	}
+	if (d > edge - 0.2) {
+		k -= 1.0;
+	}
+
+	vec3 col = texture(sampler2D(texture_, sampler_), vec2(max(k, 1.0) / 7.0, 0.0)).rgb;
+	COLOR = vec4(col, step(d, edge));
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
@@ -0,0 +1,44 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+layout(set = 2, binding = 0) uniform float pixels;
+layout(set = 2, binding = 1) uniform float inner;
+layout(set = 2, binding = 2) uniform vec2 light_origin;
+
+layout(set = 2, binding = 3) uniform texture2D texture_;
+layout(set = 2, binding = 4) uniform sampler sampler_;
+
+bool dither(vec2 uv1, vec2 uv2) {
+	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
+}
+
+void main() {
+	// pixelizing and dithering
+	vec2 uv = floor(UV * pixels) / pixels;
+	bool dith = dither(UV, uv);
+
+	// 0 at the planet's edge, 1 where the glow ends
+	float d = distance(uv, vec2(0.5)) * 2.0;
+	float t = (d - inner) / (1.0 - inner);
+	if (dith) {
+		t *= 1.1;
+	}
+
+	// brighter on the side the planet is lit from
+	float light = clamp(1.0 - distance(uv, light_origin) * 1.5, 0.0, 1.0);
+
+	// fade out in a few hard steps
+	float glow = floor(clamp(1.0 - t, 0.0, 1.0) * 3.0) / 3.0;
+	float a = glow * (0.1 + light * 0.7) * step(inner, d) * step(d, 1.0);
+
+	float k = 5.0 + floor(light * 1.99);
+	vec3 col = texture(sampler2D(texture_, sampler_), vec2(k / 7.0, 0.0)).rgb;
+	COLOR = vec4(col, a);
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
@@ -10,6 +10,7 @@ layout(set = 2, binding = 4) uniform texture2D star;
 layout(set = 2, binding = 5) uniform sampler starSampler;
 
 layout(set = 2, binding = 6) uniform int starType;
+layout(set = 2, binding = 7) uniform float brightness;
 
 const vec2 sheetSize = vec2(144, 24);
 const vec2 spriteSize = vec2(24, 24);
@@ -35,10 +36,15 @@ vec2 spriteSheetUV() {
 
 void main() {
   vec2 uv = spriteSheetUV();
-  float col_val = texture(sampler2D(star, starSampler), uv).r;
+  float col_val = clamp(texture(sampler2D(star, starSampler), uv).r * brightness, 0.0, 1.0);
   float a = texture(star, uv).a;
   vec4 replace_col = texture(sampler2D(texture_, sampler_), vec2(round(col_val * 7.0) / 7.0, 0.0));
 
   COLOR = vec4(replace_col.rgb, a);
 
+  // for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+  COLOR.rgb *= COLOR.a;
+#endif
+
 }
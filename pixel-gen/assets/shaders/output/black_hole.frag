@@ -0,0 +1,120 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+layout(set = 2, binding = 0) uniform float pixels;
+layout(set = 2, binding = 1) uniform float seed;
+layout(set = 2, binding = 2) uniform float time;
+layout(set = 2, binding = 3) uniform float horizon;
+layout(set = 2, binding = 4) uniform float tilt;
+layout(set = 2, binding = 5) uniform float rotation;
+
+layout(set = 2, binding = 6) uniform texture2D texture_;
+layout(set = 2, binding = 7) uniform sampler sampler_;
+
+float rand(vec2 coord) {
+	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * 43758.5453);
+}
+
+float noise(vec2 coord){
+	vec2 i = floor(coord);
+	vec2 f = fract(coord);
+
+	float a = rand(i);
+	float b = rand(i + vec2(1.0, 0.0));
+	float c = rand(i + vec2(0.0, 1.0));
+	float d = rand(i + vec2(1.0, 1.0));
+
+	vec2 cubic = f * f * (3.0 - 2.0 * f);
+
+	return mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y;
+}
+
+float fbm(vec2 coord){
+	float value = 0.0;
+	float scale = 0.5;
+
+	for(int i = 0; i < 3 ; i++){
+		value += noise(coord) * scale;
+		coord *= 2.0;
+		scale *= 0.5;
+	}
+	return value;
+}
+
+bool dither(vec2 uv1, vec2 uv2) {
+	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
+}
+
+// gas streaking round at radius r, the inner orbits faster: 1 / r turns for
+// each turn of time, blended between whole numbers of turns so every orbit
+// comes back round as time wraps at TAU
+float swirl(float angle, float r) {
+	float speed = 1.0 / max(r, 0.1);
+	float slow = floor(speed);
+	vec2 orbit = vec2(r * 8.0, seed);
+	float a = fbm(vec2(cos(angle + time * slow), sin(angle + time * slow)) * 3.0 + orbit);
+	float fast = angle + time * (slow + 1.0);
+	float b = fbm(vec2(cos(fast), sin(fast)) * 3.0 + orbit);
+	return mix(a, b, speed - slow);
+}
+
+vec4 color(float density) {
+	float k = clamp(1.0 + floor(density * 6.0), 1.0, 7.0);
+	return vec4(texture(sampler2D(texture_, sampler_), vec2(k / 7.0, 0.0)).rgb, 1.0);
+}
+
+void main() {
+	// pixelizing and dithering
+	vec2 uv = floor(UV * pixels) / pixels;
+	bool dith = dither(UV, uv);
+
+	// y up, and the rotation undone, so the disk lies along x with its near
+	// side below
+	vec2 p = (uv - 0.5) * vec2(2.0, -2.0);
+	p = vec2(cos(rotation) * p.x + sin(rotation) * p.y, -sin(rotation) * p.x + cos(rotation) * p.y);
+	float r = length(p);
+
+	// the disk in its own plane, where it is a circle of radius 1
+	vec2 q = vec2(p.x, p.y / tilt);
+	float r_disk = length(q);
+	float n = swirl(atan(q.y, q.x), r_disk);
+	// hotter towards the middle, with a ragged outer edge
+	float density = (1.0 - (r_disk - horizon) / (1.0 - horizon)) * 0.7 + n * 0.5;
+	if (dith) {
+		density *= 0.9;
+	}
+	bool in_disk = r_disk > horizon * 1.3 && r_disk < 0.7 + n * 0.4;
+
+	// light from the far side of the disk bent up over the top of the hole,
+	// and a thin ring of it right at the edge of the horizon
+	float arc = horizon * (1.15 + 0.5 * max(p.y / max(r, 0.001), 0.0));
+	float lensed = swirl(atan(p.y, p.x), r / horizon * 0.3) * 0.6 + 0.4;
+	if (dith) {
+		lensed *= 0.9;
+	}
+
+	vec4 col = vec4(0.0);
+	if (in_disk && q.y > 0.0) {
+		col = color(density);
+	}
+	if (r > horizon && r < horizon * 1.12) {
+		col = color(1.0);
+	} else if (r > horizon && r < arc) {
+		col = color(lensed);
+	}
+	if (r <= horizon) {
+		col = vec4(texture(sampler2D(texture_, sampler_), vec2(0.0, 0.0)).rgb, 1.0);
+	}
+	if (in_disk && q.y <= 0.0) {
+		col = color(density);
+	}
+
+	COLOR = col;
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
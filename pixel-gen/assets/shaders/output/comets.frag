@@ -0,0 +1,49 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+// pixels along and across the streak
+layout(set = 2, binding = 0) uniform vec2 pixels;
+layout(set = 2, binding = 1) uniform int head;
+// gradient bands at the end of the tail and at the head
+layout(set = 2, binding = 2) uniform vec2 colors;
+layout(set = 2, binding = 3) uniform float fade;
+
+layout(set = 2, binding = 4) uniform texture2D texture_;
+layout(set = 2, binding = 5) uniform sampler sampler_;
+
+void main() {
+	// pixelizing, sampling at the middle of each pixel so the streak is symmetric
+	vec2 cell = floor(UV * pixels);
+	vec2 uv = (cell + 0.5) / pixels;
+	bool dith = mod(cell.x + cell.y, 2.0) < 1.0;
+
+	// x runs from the end of the tail (0) to the head (1)
+	float along = uv.x;
+	float across = abs(uv.y - 0.5) * 2.0;
+
+	// a tail that narrows away from the head and breaks up towards its end
+	float a = step(across, along);
+	if (dith && along < 0.5) {
+		a = 0.0;
+	}
+	float k = floor(mix(colors.x, colors.y, along));
+
+	// a round head as wide as the streak
+	if (head == 1) {
+		vec2 centre = vec2(pixels.x - pixels.y * 0.5, pixels.y * 0.5);
+		if (distance(cell + 0.5, centre) <= pixels.y * 0.5) {
+			a = 1.0;
+			k = colors.y;
+		}
+	}
+
+	vec3 col = texture(sampler2D(texture_, sampler_), vec2(k / 7.0, 0.0)).rgb;
+	COLOR = vec4(col, a * fade);
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
@@ -0,0 +1,92 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+layout(set = 2, binding = 0) uniform float pixels;
+layout(set = 2, binding = 1) uniform float seed;
+layout(set = 2, binding = 2) uniform int arms;
+layout(set = 2, binding = 3) uniform float twist;
+layout(set = 2, binding = 4) uniform float bulge;
+layout(set = 2, binding = 5) uniform float tilt;
+layout(set = 2, binding = 6) uniform float rotation;
+layout(set = 2, binding = 7) uniform int elliptical;
+
+layout(set = 2, binding = 8) uniform texture2D texture_;
+layout(set = 2, binding = 9) uniform sampler sampler_;
+
+float rand(vec2 coord) {
+	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * 43758.5453);
+}
+
+float noise(vec2 coord){
+	vec2 i = floor(coord);
+	vec2 f = fract(coord);
+
+	float a = rand(i);
+	float b = rand(i + vec2(1.0, 0.0));
+	float c = rand(i + vec2(0.0, 1.0));
+	float d = rand(i + vec2(1.0, 1.0));
+
+	vec2 cubic = f * f * (3.0 - 2.0 * f);
+
+	return mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y;
+}
+
+float fbm(vec2 coord){
+	float value = 0.0;
+	float scale = 0.5;
+
+	for(int i = 0; i < 3 ; i++){
+		value += noise(coord) * scale;
+		coord *= 2.0;
+		scale *= 0.5;
+	}
+	return value;
+}
+
+bool dither(vec2 uv1, vec2 uv2) {
+	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
+}
+
+void main() {
+	// pixelizing and dithering
+	vec2 uv = floor(UV * pixels) / pixels;
+	bool dith = dither(UV, uv);
+
+	// undo the rotation and the tilt, so the disk is a circle of radius 1
+	vec2 p = (uv - 0.5) * 2.0;
+	p = vec2(cos(rotation) * p.x + sin(rotation) * p.y, -sin(rotation) * p.x + cos(rotation) * p.y);
+	p.y /= tilt;
+	float r = length(p);
+
+	// clumps of stars and dust
+	float n = fbm(p * 3.0 + seed);
+	// bright core
+	float core = clamp(1.0 - r / bulge, 0.0, 1.0);
+
+	float density = 0.0;
+	if (elliptical == 1) {
+		float falloff = clamp(1.0 - r, 0.0, 1.0);
+		density = falloff * falloff * (0.8 + n * 0.4) + core * 0.5;
+	} else {
+		// arms winding further round the further out they are
+		float angle = atan(p.y, p.x);
+		float arm = pow(0.5 + 0.5 * cos(float(arms) * (angle - twist * r)), 4.0);
+		float disk = arm * clamp(1.0 - r, 0.0, 1.0) * (0.4 + n * 1.2);
+		density = disk + core;
+	}
+
+	if (dith) {
+		density *= 0.9;
+	}
+
+	float k = min(1.0 + floor(density * 5.0), 7.0);
+	vec3 col = texture(sampler2D(texture_, sampler_), vec2(k / 7.0, 0.0)).rgb;
+	COLOR = vec4(col, step(0.1, density));
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
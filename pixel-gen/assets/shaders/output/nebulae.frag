@@ -13,14 +13,27 @@ layout(set = 2, binding = 7) uniform vec2 uv_correct;
 layout(set = 2, binding = 8) uniform int should_tile;
 layout(set = 2, binding = 9) uniform int reduce_background;
 
+// through the flow's loop, from 0 to 1
 layout(set = 2, binding = 11) uniform float time;
+layout(set = 2, binding = 12) uniform int transparent;
+layout(set = 2, binding = 13) uniform vec2 scroll;
+layout(set = 2, binding = 14) uniform float flow;
 
 layout(set = 2, binding = 1) uniform texture2D texture_;
 layout(set = 2, binding = 2) uniform sampler sampler_;
 
+// noise cells across the quad at this scale, whole ones when tiling so the
+// noise wraps where the tile does
+vec2 cells(float scale) {
+    if (should_tile == 1) {
+        return max(round(scale * uv_correct), vec2(1.0));
+    }
+    return scale * uv_correct;
+}
+
 float rand(vec2 coord, float tilesize) {
     if (should_tile == 1) {
-        coord = mod(coord / uv_correct, tilesize);
+        coord = mod(coord, cells(tilesize));
     }
 
     return fract(sin(dot(coord.xy, vec2(12.9898, 78.233))) * (15.5453 + seed));
@@ -37,7 +50,7 @@ float noise(vec2 coord, float tilesize) {
 
     vec2 cubic = f * f * (3.0 - 2.0 * f);
 
-    return (mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y) + time;
+    return mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y;
 }
 
 float fbm(vec2 coord, float tilesize) {
@@ -58,7 +71,7 @@ bool dither(vec2 uv1, vec2 uv2) {
 
 float circleNoise(vec2 uv, float tilesize) {
     if (should_tile == 1) {
-        uv = mod(uv, tilesize / uv_correct);
+        uv = mod(uv, cells(tilesize));
     }
 
     float uv_y = floor(uv.y);
@@ -82,29 +95,52 @@ float cloud_alpha(vec2 uv, float tilesize) {
 
     int iters = 2;
     for (int i = 0; i < iters; i++) {
-        c_noise += circleNoise(uv * 0.5 + (float(i+1)) + vec2(-0.3, 0.0), ceil(tilesize * 0.5));
+        c_noise += circleNoise(uv * cells(tilesize * 0.5) + (float(i+1)) + vec2(-0.3, 0.0), ceil(tilesize * 0.5));
     }
-    float fbm = fbm(uv+c_noise, tilesize);
+    float fbm = fbm(uv * cells(tilesize) + c_noise, tilesize);
 
     return fbm;
 }
 
+// How far the flow pushes the clouds at `uv`, in uv. The push is noise sampled
+// round a circle as `time` goes from 0 to 1, so the flow comes back to where
+// it started, and wraps with the tile like the rest of the noise.
+vec2 flow_warp(vec2 uv, float tilesize) {
+    if (flow <= 0.0) {
+        return vec2(0.0);
+    }
+
+    float angle = time * 6.28318530718;
+    vec2 coord = uv * cells(tilesize) + vec2(cos(angle), sin(angle));
+    vec2 push = vec2(fbm(coord + vec2(1.7, 9.2), tilesize), fbm(coord + vec2(8.3, 2.8), tilesize));
+    return (push - 0.5) * flow / cells(tilesize);
+}
+
 
 
 void main() {
-	vec2 uv = floor((UV) * pixels) / pixels;
+	// scrolled by parallax mode, which always tiles
+	vec2 scrolled = UV + scroll;
+	vec2 cell = floor(scrolled * pixels);
+	// a tile over samples the very same cells, not ones a rounding error away
+	if (should_tile == 1) {
+		cell = mod(cell, pixels);
+	}
+	vec2 uv = cell / pixels;
 	
 	// distance from center
 	float d =  distance(uv, vec2(0.5)) * 0.4;
 	
-	uv *= uv_correct;
-	bool dith = dither(uv, UV);
-	
+	// the uncorrected grid repeats every tile
+	bool dith = dither(should_tile == 1 ? uv : uv * uv_correct, scrolled);
+
+	uv += flow_warp(uv, size);
+
 	// noise for the inside of the nebulae
-	float n = cloud_alpha(uv * size, size);
-	float n2 = fbm(uv * size + vec2(1, 1), size);
+	float n = cloud_alpha(uv, size);
+	float n2 = fbm(uv * cells(size) + vec2(1, 1), size);
 	float n_lerp = n2 * n;
-	float n_dust = cloud_alpha(uv * size, size);
+	float n_dust = cloud_alpha(uv, size);
 	float n_dust_lerp = n_dust * n_lerp;
 
 	// apply dithering
@@ -139,9 +175,17 @@ void main() {
 	vec3 col = texture(sampler2D(texture_, sampler_), vec2(col_value, 0.0)).rgb;
 	if (col_value < 0.1) {
 		col = background_color.rgb;
+		// with nothing behind the nebulae, fade its darkest band out in steps
+		// instead of painting the background over whatever is underneath
+		if (transparent == 1) {
+			a2 *= min(floor(n_dust_lerp * 56.0) / 4.0, 1.0);
+		}
 	}
 
   COLOR = vec4(col, a2);
-}
-
 
+  // for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+  COLOR.rgb *= COLOR.a;
+#endif
+}
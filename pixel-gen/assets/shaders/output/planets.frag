@@ -12,31 +12,48 @@ layout(set = 2, binding = 5) uniform texture2D texture_;
 layout(set = 2, binding = 6) uniform sampler sampler_;
 
 layout(set = 2, binding = 4) uniform vec2 light_origin;
-
-float rand(vec2 coord) {
+layout(set = 2, binding = 8) uniform int kind;
+// axial tilt, in radians
+layout(set = 2, binding = 10) uniform float tilt;
+// how far the planet has turned, from 0 to 1
+layout(set = 2, binding = 11) uniform float time;
+
+// matches `PlanetKind`
+#define DRY 0
+#define TERRAN 1
+#define ICE 2
+#define LAVA 3
+#define GAS_GIANT 4
+
+// uv across the whole way round; the visible face is half of it
+#define TURN 2.0
+
+// wraps every `period` along x, so the surface meets itself round the back
+float rand(vec2 coord, float period) {
+	coord.x = mod(coord.x, period);
 	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * 15.5453 * seed);
 }
 
-float noise(vec2 coord){
+float noise(vec2 coord, float period){
 	vec2 i = floor(coord);
 	vec2 f = fract(coord);
 		
-	float a = rand(i);
-	float b = rand(i + vec2(1.0, 0.0));
-	float c = rand(i + vec2(0.0, 1.0));
-	float d = rand(i + vec2(1.0, 1.0));
+	float a = rand(i, period);
+	float b = rand(i + vec2(1.0, 0.0), period);
+	float c = rand(i + vec2(0.0, 1.0), period);
+	float d = rand(i + vec2(1.0, 1.0), period);
 
 	vec2 cubic = f * f * (3.0 - 2.0 * f);
 
 	return mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y;
 }
 
-float fbm(vec2 coord){
+float fbm(vec2 coord, float period){
 	float value = 0.0;
 	float scale = 0.5;
 
 	for(int i = 0; i < OCTAVES ; i++){
-		value += noise(coord) * scale;
+		value += noise(coord, period) * scale;
 		coord *= 2.0;
 		scale *= 0.5;
 	}
@@ -47,16 +64,6 @@ bool dither(vec2 uv1, vec2 uv2) {
 	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
 }
 
-float circleNoise(vec2 uv) {
-    float uv_y = floor(uv.y);
-    uv.x += uv_y*.31;
-    vec2 f = fract(uv);
-	float h = rand(vec2(floor(uv.x),floor(uv_y)));
-    float m = (length(f-0.25-(h*0.5)));
-    float r = h*0.25;
-    return smoothstep(0.0, r, m*0.75);
-}
-
 vec2 rotate(vec2 vec, float angle) {
 	vec -=vec2(0.5);
 	vec *= mat2(vec2(cos(angle),-sin(angle)), vec2(sin(angle),cos(angle)));
@@ -73,21 +80,98 @@ vec2 spherify(vec2 uv) {
 	return sphere * 0.5+0.5;
 }
 
-float cloud_alpha(vec2 uv) {
-	float c_noise = 0.0;
-	
-	// more iterations for more turbulence
-	int iters = 2;
-	for (int i = 0; i < iters; i++) {
-		float relative = (float(i)/float(iters));
-		vec2 c_uv = rotate(uv, relative * 6.28);
-		c_noise += circleNoise((uv * 0.3) + (float(i+1)+10.));
+// the noise a planet's surface is built from; what the two values mean
+// depends on the kind
+vec2 surface(vec2 uv) {
+	// a whole number of noise cells round the planet at every scale below
+	vec2 scale = vec2(max(round(size), 1.0), size);
+	float period = scale.x * TURN;
+	float n = fbm(uv * scale, period);
+	switch (kind) {
+	case TERRAN:
+		// land height, polar cap edge
+		return vec2(fbm(uv * scale + n * 2.0, period), n);
+	case ICE:
+		// frost, cracks
+		return vec2(n, fbm(uv * scale * 1.5 + 7.0, period * 1.5));
+	case LAVA:
+		// crust, rivers
+		return vec2(n, fbm(uv * scale * 1.5 + n * 2.0 + 3.0, period * 1.5));
+	case GAS_GIANT:
+		// turbulence, stretched so it runs along the bands
+		return vec2(fbm(uv * scale * vec2(0.5, 2.0), period * 0.5), 0.0);
+	default:
+		return vec2(n, fbm(uv * scale + n * 3.0, period));
 	}
-	float fbm = fbm(uv+c_noise);
-	
-	return fbm;
 }
 
+// where on the surface a point of the spherified face is, once tilted and turned
+vec2 turn(vec2 uv) {
+	uv = rotate(uv, tilt);
+	uv.x -= time * TURN;
+	return uv;
+}
+
+// lit side to dark side in four steps
+float light_level(float d_to_light, bool dith) {
+	float light = clamp(1.0 - d_to_light, 0.0, 1.0);
+	if (dith) {
+		light *= 0.95;
+	}
+	return floor(light * 3.99);
+}
+
+// position in the gradient, darkest color at 0 and brightest at 7 / 7
+float col_value(vec2 n, vec2 uv, float d_to_light, bool dith) {
+	float light = light_level(d_to_light, dith);
+	float k = 0.0;
+
+	switch (kind) {
+	case TERRAN:
+		if (abs(uv.y - 0.5) > 0.38 - n.y * 0.15) {
+			k = 5.0 + light;
+		} else if (n.x < 0.45) {
+			k = 1.0 + floor(light * 0.5);
+		} else {
+			k = 3.0 + light;
+		}
+		break;
+	case ICE:
+		k = 4.0 + light;
+		if (n.x < 0.35) {
+			k -= 1.0;
+		}
+		if (abs(n.y - 0.45) < 0.015) {
+			k = 2.0 + floor(light * 0.5);
+		}
+		break;
+	case LAVA:
+		// rivers glow, so they ignore the light
+		float river = abs(n.y - 0.45);
+		if (river < 0.012) {
+			k = 7.0;
+		} else if (river < 0.024) {
+			k = 5.0;
+		} else {
+			k = floor(light * 0.67) + step(0.5, n.x);
+		}
+		break;
+	case GAS_GIANT:
+		float bands = uv.y * size * 0.75 + n.x * 1.5;
+		k = 1.0 + floor(fract(bands * 0.5) * 3.0) + light;
+		break;
+	default:
+		float mixed = n.y * d_to_light;
+		// optionally create some contrast with this
+	//	mixed = pow(mixed, 1.0);
+		if (dith) {
+			mixed *= 0.95;
+		}
+		return floor(mixed * 15.0) / 7.0;
+	}
+
+	return min(k, 7.0) / 7.0;
+}
 
 void main() {
 	/// pixelzing and dithering
@@ -103,24 +187,19 @@ void main() {
 	// bit of contrast
 	d_to_light += pow(d_to_center * 3.0, 4.0) * 0.05;
 	
-	// noise for the planet
-	float n = fbm(uv * size);
-	float n2 = fbm(uv * size + n*3.0);
-	float mixed = n2 * d_to_light;
-	
-	// optionally create some contrast with this
-//	mixed = pow(mixed, 1.0);
-
-	// apply dithering
-	if (dith) {
-		mixed *= 0.95;
-	}
+	// the surface turns under the light, which stays put
+	vec2 surface_uv = turn(uv);
 	
 	// choose and apply colors
-	float col_val = floor(mixed * 15.0) / 7.0;
+	float col_val = col_value(surface(surface_uv), surface_uv, d_to_light, dith);
 	vec3 col = texture(sampler2D(texture_, sampler_), vec2(col_val, 0.0)).rgb;
 	
 	// apply alpha
 	float a = step(d_to_center, 0.5);
 	COLOR = vec4(col, a);
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
 }
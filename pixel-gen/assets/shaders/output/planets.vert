@@ -7,6 +7,7 @@ layout(location = 2) in vec2 Vertex_Uv;
 layout(location = 0) out vec2 UV;
 
 layout(set = 2, binding = 7) uniform vec3 position;
+layout(set = 2, binding = 9) uniform float scale;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
     mat4 ViewProj;
@@ -20,8 +21,8 @@ layout(set = 0, binding = 0) uniform CameraViewProj {
 
 void main() {
     UV = Vertex_Uv;
-    // Translate the vertex position by the object's position
-    vec4 worldPosition = vec4(Vertex_Position + position, 1.0);
+    // Scale around the planet's centre, then translate to it
+    vec4 worldPosition = vec4(Vertex_Position * scale + position, 1.0);
     // Transform the position to clip space using the ViewProj matrix
     gl_Position = ViewProj * worldPosition;
 }
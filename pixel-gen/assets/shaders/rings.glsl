@@ -0,0 +1,60 @@
+#version 450
+
+layout(location = 0) in vec2 UV;
+layout(location = 0) out vec4 COLOR;
+
+layout(set = 2, binding = 0) uniform float pixels;
+layout(set = 2, binding = 1) uniform float inner;
+layout(set = 2, binding = 2) uniform float tilt;
+layout(set = 2, binding = 3) uniform float inclination;
+layout(set = 2, binding = 4) uniform float seed;
+layout(set = 2, binding = 5) uniform int front;
+
+layout(set = 2, binding = 6) uniform texture2D texture_;
+layout(set = 2, binding = 7) uniform sampler sampler_;
+
+float rand(vec2 coord) {
+	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * 15.5453 * seed);
+}
+
+bool dither(vec2 uv1, vec2 uv2) {
+	return mod(uv1.y+uv2.x,2.0/pixels) <= 1.0 / pixels;
+}
+
+void main() {
+	// pixelizing and dithering
+	vec2 uv = floor(UV * pixels) / pixels;
+	bool dith = dither(UV, uv);
+
+	// undo the tilt and the squash, so the ring is a circle of radius 1
+	vec2 p = (uv - 0.5) * 2.0;
+	p = vec2(cos(tilt) * p.x + sin(tilt) * p.y, -sin(tilt) * p.x + cos(tilt) * p.y);
+	p.y /= inclination;
+	float r = length(p);
+
+	// the far half goes behind the planet, the near half in front of it
+	float a = step(inner, r) * step(r, 1.0);
+	if (front == 1) {
+		a *= step(p.y, 0.0);
+	} else {
+		a *= 1.0 - step(p.y, 0.0);
+	}
+
+	// concentric bands of varying brightness, with a few gaps
+	float band = rand(vec2(floor((r - inner) / (1.0 - inner) * 12.0), 1.0));
+	if (band < 0.15) {
+		a = 0.0;
+	}
+	float k = 3.0 + floor(band * 3.99);
+	if (dith && fract(band * 7.0) < 0.5) {
+		k -= 1.0;
+	}
+
+	vec3 col = texture(sampler2D(texture_, sampler_), vec2(k / 7.0, 0.0)).rgb;
+	COLOR = vec4(col, a);
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
+}
@@ -10,13 +10,27 @@ layout(set = 2, binding = 5) uniform float pixels;
 layout(set = 2, binding = 6) uniform vec2 uv_correct;
 layout(set = 2, binding = 7) uniform int should_tile;
 layout(set = 2, binding = 8) uniform int reduce_background;
+layout(set = 2, binding = 10) uniform int transparent;
+layout(set = 2, binding = 11) uniform vec2 scroll;
+// through the flow's loop, from 0 to 1
+layout(set = 2, binding = 12) uniform float time;
+layout(set = 2, binding = 13) uniform float flow;
 
 layout(set = 2, binding = 1) uniform texture2D texture_;
 layout(set = 2, binding = 2) uniform sampler sampler_;
 
+// noise cells across the quad at this scale, whole ones when tiling so the
+// noise wraps where the tile does
+vec2 cells(float scale) {
+	if (should_tile == 1) {
+		return max(round(scale * uv_correct), vec2(1.0));
+	}
+	return scale * uv_correct;
+}
+
 float rand(vec2 coord, float tilesize) {
 	if (should_tile == 1) {
-		coord = mod(coord / uv_correct, tilesize );
+		coord = mod(coord, cells(tilesize));
 	}
 
 	return fract(sin(dot(coord.xy ,vec2(12.9898,78.233))) * (15.5453 + seed));
@@ -54,7 +68,7 @@ bool dither(vec2 uv1, vec2 uv2) {
 
 float circleNoise(vec2 uv, float tilesize) {
 	if (should_tile == 1) {
-		uv = mod(uv, tilesize);
+		uv = mod(uv, cells(tilesize));
 	}
 	
     float uv_y = floor(uv.y);
@@ -80,23 +94,46 @@ float cloud_alpha(vec2 uv, float tilesize) {
 	// more iterations for more turbulence
 	int iters = 2;
 	for (int i = 0; i < iters; i++) {
-		c_noise += circleNoise(uv * 0.5 + (float(i+1)) + vec2(-0.3, 0.0), ceil(tilesize * 0.5));
+		c_noise += circleNoise(uv * cells(tilesize * 0.5) + (float(i+1)) + vec2(-0.3, 0.0), ceil(tilesize * 0.5));
 	}
-	float fbm = fbm(uv+c_noise, tilesize);
+	float fbm = fbm(uv * cells(tilesize) + c_noise, tilesize);
 	
 	return fbm;
 }
 
+// How far the flow pushes the dust at `uv`, in uv, sampled round a circle as
+// `time` goes from 0 to 1 so it loops, see `nebulae.glsl`.
+vec2 flow_warp(vec2 uv, float tilesize) {
+	if (flow <= 0.0) {
+		return vec2(0.0);
+	}
+
+	float angle = time * 6.28318530718;
+	vec2 coord = uv * cells(tilesize) + vec2(cos(angle), sin(angle));
+	vec2 push = vec2(fbm(coord + vec2(1.7, 9.2), tilesize), fbm(coord + vec2(8.3, 2.8), tilesize));
+	return (push - 0.5) * flow / cells(tilesize);
+}
+
 void main() {
 	// pixelizing and dithering
-	vec2 uv = floor((UV) * pixels) / pixels * uv_correct;
-	bool dith = dither(uv, UV);
-	
+	// scrolled by parallax mode, which always tiles
+	vec2 scrolled = UV + scroll;
+	vec2 cell = floor(scrolled * pixels);
+	// a tile over samples the very same cells, not ones a rounding error away
+	if (should_tile == 1) {
+		cell = mod(cell, pixels);
+	}
+	vec2 uv = cell / pixels;
+	// the uncorrected grid repeats every tile
+	bool dith = dither(should_tile == 1 ? uv : uv * uv_correct, scrolled);
+
+	uv += flow_warp(uv, size);
+
 	// noise for the dust
 	// the + vec2(x,y) is to create an offset in noise values
-	float n_alpha = fbm(uv * ceil(size * 0.5) +vec2(2,2), ceil(size * 0.5));
-	float n_dust = cloud_alpha(uv * size, size);
-	float n_dust2 = fbm(uv * ceil(size * 0.2)  -vec2(2,2),ceil(size * 0.2));
+	float n_alpha = fbm(uv * cells(ceil(size * 0.5)) +vec2(2,2), ceil(size * 0.5));
+	float n_dust = cloud_alpha(uv, size);
+	float n_dust2 = fbm(uv * cells(ceil(size * 0.2))  -vec2(2,2),ceil(size * 0.2));
 	float n_dust_lerp = n_dust2 * n_dust;
 
 	// apply dithering
@@ -118,6 +155,16 @@ void main() {
 	
 	float col_value = floor(n_dust_lerp) / 7.0;
 	vec3 col = texture(sampler2D(texture_, sampler_), vec2(col_value, 0.0)).rgb;
+
+	// the darkest band is the background color, so fade it out in steps
+	if (transparent == 1 && col_value < 0.1) {
+		a_dust *= clamp(floor(n_dust_lerp * 4.0) / 4.0, 0.0, 1.0);
+	}
 	
 	COLOR = vec4(col, a_dust);
+
+	// for blend modes other than alpha, see `BlendMode::specialize`
+#ifdef PREMULTIPLY_ALPHA
+	COLOR.rgb *= COLOR.a;
+#endif
 }
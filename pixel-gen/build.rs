@@ -41,4 +41,64 @@ fn main() {
         "assets/shaders/output/big_star.vert",
     )
     .unwrap();
+    std::fs::copy(
+        "assets/shaders/rings.glsl",
+        "assets/shaders/output/rings.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/rings.vert.glsl",
+        "assets/shaders/output/rings.vert",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/atmosphere.glsl",
+        "assets/shaders/output/atmosphere.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/atmosphere.vert.glsl",
+        "assets/shaders/output/atmosphere.vert",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/asteroids.glsl",
+        "assets/shaders/output/asteroids.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/asteroids.vert.glsl",
+        "assets/shaders/output/asteroids.vert",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/galaxies.glsl",
+        "assets/shaders/output/galaxies.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/galaxies.vert.glsl",
+        "assets/shaders/output/galaxies.vert",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/black_hole.glsl",
+        "assets/shaders/output/black_hole.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/black_hole.vert.glsl",
+        "assets/shaders/output/black_hole.vert",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/comets.glsl",
+        "assets/shaders/output/comets.frag",
+    )
+    .unwrap();
+    std::fs::copy(
+        "assets/shaders/comets.vert.glsl",
+        "assets/shaders/output/comets.vert",
+    )
+    .unwrap();
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use bevy::{prelude::*, window::WindowMode};
 use pixel_gen::*;
 
@@ -15,12 +17,18 @@ fn main() {
 }
 
 const OPTIONS: pixel_gen::Options = Options {
+    seed: GenerationSeed(0),
     pixels: 400.,
-    colorscheme: ColorScheme::FunkyFutures,
+    colorscheme: ColorScheme::FUNKY_FUTURES,
     stars: true,
     dust: true,
     nebulae: true,
     planets: true,
+    asteroids: true,
+    galaxies: true,
+    black_hole: false,
+    comets: true,
+    parallax: false,
     tile: false,
     darken: false,
     transparency: false,
@@ -29,4 +37,16 @@ const OPTIONS: pixel_gen::Options = Options {
         screen_space: utils::screenspace::CommonResolution::FourK16x9.space(),
         show_ui: false,
     },
+    nebulae_settings: NebulaeSettings::DEFAULT,
+    dust_settings: DustSettings::DEFAULT,
+    planet_settings: PlanetSettings::DEFAULT,
+    star_settings: StarSettings::DEFAULT,
+    asteroid_settings: AsteroidSettings::DEFAULT,
+    galaxy_settings: GalaxySettings::DEFAULT,
+    black_hole_settings: BlackHoleSettings::DEFAULT,
+    comet_settings: CometSettings::DEFAULT,
+    parallax_settings: ParallaxSettings::DEFAULT,
+    layer_z: LayerZ::DEFAULT,
+    blend_modes: BlendModes::DEFAULT,
+    layers: BTreeMap::new(),
 };
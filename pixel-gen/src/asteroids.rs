@@ -0,0 +1,211 @@
+use std::{f32::consts::TAU, ops::Range};
+
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    *,
+};
+
+#[derive(Component)]
+pub struct Asteroid;
+
+#[derive(SystemParam)]
+pub struct AsteroidSpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    mats: ResMut<'w, Assets<AsteroidMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+impl BackgroundLayer for Asteroid {
+    const NAME: &'static str = "asteroids";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyR);
+
+    type Param = AsteroidSpawner<'static>;
+
+    fn spawn(commands: &mut Commands, options: &config::ResolvedOptions, sa: &mut AsteroidSpawner) {
+        for material in generate(options, options.palette(), &mut sa.images) {
+            let layer = ParallaxLayer::new(
+                options.parallax_settings.asteroids,
+                material.position,
+                options.screen_size.rect(),
+            )
+            .wrapping(material.position.truncate(), material.radius);
+            let mesh = MaterialMesh2dBundle {
+                mesh: sa
+                    .meshes
+                    .add(Rectangle::from_length(material.radius * 2.))
+                    .into(),
+                material: sa.mats.add(material),
+                // mid-ground: over the big stars, under the planets
+                transform: Transform::from_translation(
+                    Vec3::ZERO.with_z(options.layer_z.asteroids),
+                ),
+                ..default()
+            };
+
+            commands.spawn((mesh, Asteroid, layer));
+        }
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.asteroids
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.asteroids = enabled;
+    }
+}
+
+/// Materials of every rock for `options`, drawn from the "asteroids" seed stream.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> Vec<AsteroidMaterial> {
+    let mut rng = options.seed.stream("asteroids");
+    let settings = &options.asteroid_settings;
+    let color_gradiant = images.add(palette.gradient_image_with_bg().0);
+    // every rock is lit from the same side
+    let light_origin = Vec2::new(rng.gen(), rng.gen());
+    let mut rocks = Vec::new();
+
+    for _ in 0..config::sample(&mut rng, &settings.belts) {
+        // an arc around a point somewhere on screen, wide enough to cross it
+        let centre = options.screen_size.random_postion(&mut rng, 0.).truncate();
+        let radius = options.screen_size.height() * rng.gen_range(0.6..1.2);
+        let start = rng.gen_range(0.0..TAU);
+        let sweep = rng.gen_range(1.0..2.0);
+        let spread = config::sample(&mut rng, &settings.belt_width).max(0.) / 2.;
+
+        for _ in 0..config::sample(&mut rng, &settings.belt_rocks) {
+            let angle = start + rng.gen_range(0.0..sweep);
+            // two draws bunch the rocks towards the middle of the belt
+            let offset = (rng.gen_range(-spread..=spread) + rng.gen_range(-spread..=spread)) / 2.;
+            let position = centre + Vec2::from_angle(angle) * (radius + offset);
+            rocks.push(AsteroidMaterial::new(
+                options,
+                &mut rng,
+                position,
+                color_gradiant.clone(),
+                light_origin,
+            ));
+        }
+    }
+
+    for _ in 0..config::sample(&mut rng, &settings.clusters) {
+        let centre = options.screen_size.random_postion(&mut rng, 0.).truncate();
+        let spread = config::sample(&mut rng, &settings.cluster_size).max(0.) / 2.;
+
+        for _ in 0..config::sample(&mut rng, &settings.cluster_rocks) {
+            let offset = Vec2::from_angle(rng.gen_range(0.0..TAU)) * rng.gen_range(0.0..=spread);
+            rocks.push(AsteroidMaterial::new(
+                options,
+                &mut rng,
+                centre + offset,
+                color_gradiant.clone(),
+                light_origin,
+            ));
+        }
+    }
+
+    tile::wrapped(options, rocks, quad, |rock, shift| {
+        rock.position += shift.extend(0.)
+    })
+}
+
+/// How many rocks are scattered, in belts along an arc and in free floating clusters.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AsteroidSettings {
+    pub belts: Range<u32>,
+    pub belt_rocks: Range<u32>,
+    /// Width of a belt in screen pixels.
+    pub belt_width: Range<f32>,
+    pub clusters: Range<u32>,
+    pub cluster_rocks: Range<u32>,
+    /// Diameter of a cluster in screen pixels.
+    pub cluster_size: Range<f32>,
+    /// Radius of a rock in screen pixels.
+    pub radius: Range<f32>,
+}
+
+impl AsteroidSettings {
+    pub const DEFAULT: AsteroidSettings = AsteroidSettings {
+        belts: 0..2,
+        belt_rocks: 30..80,
+        belt_width: 30.0..80.0,
+        clusters: 1..4,
+        cluster_rocks: 3..12,
+        cluster_size: 40.0..120.0,
+        radius: 3.0..10.0,
+    };
+}
+
+impl Default for AsteroidSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// World space covered by a rock's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &AsteroidMaterial) -> Rect {
+    Rect::from_center_size(
+        material.position.truncate(),
+        Vec2::splat(material.radius * 2.),
+    )
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct AsteroidMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: f32,
+    #[uniform(1)]
+    pub(crate) seed: f32,
+    #[uniform(2)]
+    pub(crate) light_origin: Vec2,
+
+    #[texture(3)]
+    #[sampler(4)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(5)]
+    pub(crate) position: Vec3,
+
+    /// Radius in world space, for the mesh.
+    pub(crate) radius: f32,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl AsteroidMaterial {
+    fn new(
+        options: &config::Options,
+        rng: &mut impl Rng,
+        position: Vec2,
+        color_gradiant: Handle<Image>,
+        light_origin: Vec2,
+    ) -> Self {
+        let radius = config::sample(rng, &options.asteroid_settings.radius);
+        AsteroidMaterial {
+            // two screen pixels per rock pixel, like the big stars
+            pixels: radius.max(2.).floor(),
+            seed: rng.gen_range(0.0..100.0),
+            light_origin,
+            color_texture: Some(color_gradiant),
+            position: position.extend(1.75),
+            radius,
+            blend: options.blend_modes.asteroids,
+        }
+    }
+}
+
+impl ParallaxMaterial2D for AsteroidMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
+    }
+}
+
+layers::blended_material!(AsteroidMaterial, "asteroids");
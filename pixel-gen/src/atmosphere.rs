@@ -0,0 +1,110 @@
+use bevy::{reflect::TypePath, render::render_resource::AsBindGroup};
+use rand::Rng;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    planets::PlanetsMaterial,
+    shaders::{ScaledMaterial2D, Tracks},
+    *,
+};
+
+/// How far the glow reaches past the planet's surface, in planet radii.
+const THICKNESS: f32 = 0.15;
+
+/// The thin glow around a planet.
+#[derive(Component)]
+pub struct Atmosphere;
+
+/// Draws the atmosphere for `planet` from `rng`, or `None` if it has none.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+    rng: &mut impl Rng,
+    planet: &PlanetsMaterial,
+    radius: f32,
+) -> Option<AtmosphereMaterial> {
+    let has_atmosphere = rng.gen::<f32>() < options.planet_settings.atmosphere;
+
+    has_atmosphere.then(|| {
+        let outer = 1.0 + THICKNESS;
+        AtmosphereMaterial {
+            pixels: planet.pixels * outer,
+            inner: 1.0 / outer,
+            // the planet's light origin, in this larger quad's uv
+            light_origin: (planet.light_origin - 0.5) / outer + 0.5,
+            color_texture: Some(images.add(palette.gradient_image_with_bg().0)),
+            position: planet.position,
+            scale: 1.0,
+            radius: radius * outer,
+            blend: planet.blend,
+        }
+    })
+}
+
+/// World space covered by the atmosphere's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &AtmosphereMaterial) -> Rect {
+    Rect::from_center_size(
+        material.position.truncate(),
+        Vec2::splat(material.radius * 2.),
+    )
+}
+
+/// Circles the glow's light with the planet's, with `animate`.
+#[derive(Component, AnimatedMaterialConfig)]
+pub struct AtmosphereConfig {
+    #[animation]
+    tracks: Tracks,
+}
+
+impl AtmosphereConfig {
+    pub(crate) fn orbiting(material: &AtmosphereMaterial) -> Self {
+        let tracks = Tracks::new().with_orbit(
+            "light_origin",
+            Vec2::splat(0.5),
+            material.light_origin,
+            planets::ORBIT_SECONDS,
+        );
+        AtmosphereConfig { tracks }
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct AtmosphereMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: f32,
+    /// The planet's edge, as a fraction of the glow's radius.
+    #[uniform(1)]
+    pub(crate) inner: f32,
+    #[uniform(2)]
+    #[animate]
+    pub(crate) light_origin: Vec2,
+
+    #[texture(3)]
+    #[sampler(4)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(5)]
+    pub(crate) position: Vec3,
+    #[uniform(6)]
+    pub(crate) scale: f32,
+
+    /// Outer radius in world space, for the mesh.
+    pub(crate) radius: f32,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl ScaledMaterial2D for AtmosphereMaterial {
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+}
+
+impl ParallaxMaterial2D for AtmosphereMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
+    }
+}
+
+layers::blended_material!(AtmosphereMaterial, "atmosphere");
@@ -1,24 +1,46 @@
+use bevy::ecs::system::SystemParam;
+
 use crate::*;
 
 #[derive(Event)]
 pub struct SpawnBackgroundEvent;
 
+#[derive(Component)]
+pub struct Background;
+
+#[derive(SystemParam)]
+pub struct BackgroundSpawner<'w, 's> {
+    events: EventReader<'w, 's, SpawnBackgroundEvent>,
+    commands: Commands<'w, 's>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    query: Query<'w, 's, Entity, With<Background>>,
+}
+
 pub fn setup(mut writer: EventWriter<SpawnBackgroundEvent>) {
     writer.send(SpawnBackgroundEvent);
 }
 
 pub fn spawn(
-    mut reader: EventReader<SpawnBackgroundEvent>,
-    mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut bg: BackgroundSpawner,
     window: Query<&Window>,
     mut options: ResMut<config::Options>,
+    color_schemes: Res<ColorSchemes>,
 ) {
-    let Some(_) = reader.read().next() else {
+    let Some(_) = bg.events.read().next() else {
         return;
     };
-    reader.clear();
+    bg.events.clear();
+
+    if let Ok(entity) = bg.query.get_single() {
+        bg.commands.entity(entity).despawn_recursive();
+    }
+
+    // nothing is painted behind the layers, so they composite over whatever
+    // the app renders underneath
+    if options.transparency {
+        return;
+    }
 
     let Ok(window) = window.get_single() else {
         return;
@@ -27,13 +49,35 @@ pub fn spawn(
     let size = window.size();
     options.screen_size.set(size);
 
-    commands.spawn(MaterialMesh2dBundle {
-        mesh: meshes.add(Rectangle::new(size.x, size.y)).into(),
-        transform: Transform::from_xyz(0., 0.0, -1.0),
-        material: materials.add(ColorMaterial::from_color(
-            utils::colors::hex_to_color(options.colorscheme.colors().first().unwrap())
-                .with_alpha(if options.transparency { 0.0 } else { 1.0 }),
-        )),
-        ..default()
-    });
+    bg.commands.spawn((
+        Background,
+        MaterialMesh2dBundle {
+            mesh: bg.meshes.add(Rectangle::new(size.x, size.y)).into(),
+            transform: Transform::from_xyz(0., 0.0, -1.0),
+            material: bg.materials.add(ColorMaterial::from_color(color(
+                color_schemes.palette(&options.colorscheme),
+            ))),
+            ..default()
+        },
+    ));
+}
+
+/// Keeps the background behind the camera in parallax mode, since it fills the
+/// screen rather than scrolling.
+pub fn follow_camera(
+    options: Res<config::Options>,
+    parallax: Res<Parallax>,
+    mut query: Query<&mut Transform, With<Background>>,
+) {
+    if !options.parallax {
+        return;
+    }
+
+    for mut transform in query.iter_mut() {
+        transform.translation = parallax.anchor().extend(transform.translation.z);
+    }
+}
+
+pub(crate) fn color(palette: &Palette) -> Color {
+    palette.colors()[0]
 }
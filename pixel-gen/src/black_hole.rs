@@ -0,0 +1,214 @@
+use std::{f32::consts::TAU, ops::Range};
+
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    shaders::AnimatedMaterialConfig,
+    *,
+};
+
+#[derive(Component)]
+pub struct BlackHole;
+
+#[derive(SystemParam)]
+pub struct BlackHoleSpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    mats: ResMut<'w, Assets<BlackHoleMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+impl BackgroundLayer for BlackHole {
+    const NAME: &'static str = "black_hole";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyB);
+
+    type Param = BlackHoleSpawner<'static>;
+
+    fn spawn(
+        commands: &mut Commands,
+        options: &config::ResolvedOptions,
+        sb: &mut BlackHoleSpawner,
+    ) {
+        let (materials, spin) = generate(options, options.palette(), &mut sb.images);
+
+        for material in materials {
+            let animation_config = BlackHoleConfig::new(material.time, spin);
+            let layer = ParallaxLayer::new(
+                options.parallax_settings.black_hole,
+                material.position,
+                options.screen_size.rect(),
+            )
+            .wrapping(material.position.truncate(), material.radius);
+
+            commands.spawn((
+                BlackHole,
+                animation_config,
+                layer,
+                MaterialMesh2dBundle {
+                    mesh: sb
+                        .meshes
+                        .add(Rectangle::from_length(material.radius * 2.))
+                        .into(),
+                    material: sb.mats.add(material),
+                    // the focal point: over everything but the planets
+                    transform: Transform::from_translation(
+                        Vec3::ZERO.with_z(options.layer_z.black_hole),
+                    ),
+                    ..default()
+                },
+            ));
+        }
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.black_hole
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.black_hole = enabled;
+    }
+}
+
+/// The black hole material for `options` and how fast its disk turns, in
+/// radians per second, drawn from the "black_hole" seed stream. In tile mode
+/// a black hole crossing the edge comes with its copies on the other side.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> (Vec<BlackHoleMaterial>, f32) {
+    let mut rng = options.seed.stream("black_hole");
+    let settings = &options.black_hole_settings;
+
+    let horizon = config::sample(&mut rng, &settings.radius);
+    let disk = config::sample(&mut rng, &settings.disk).max(1.5);
+    let radius = horizon * disk;
+    let material = BlackHoleMaterial {
+        // two screen pixels per pixel, like the big stars
+        pixels: radius.max(1.).floor(),
+        seed: rng.gen_range(0.0..100.0),
+        time: rng.gen_range(0.0..TAU),
+        horizon: 1. / disk,
+        // never quite edge on, or the disk vanishes
+        tilt: config::sample(&mut rng, &settings.tilt)
+            .to_radians()
+            .cos()
+            .max(0.05),
+        rotation: rng.gen_range(-0.5..0.5),
+        color_texture: Some(images.add(palette.gradient_image_with_bg().0)),
+        position: options.screen_size.random_postion(&mut rng, 1.9),
+        radius,
+        blend: options.blend_modes.black_hole,
+    };
+
+    let spin = config::sample(&mut rng, &settings.spin);
+    let spin = if rng.gen() { spin } else { -spin };
+    let materials = tile::wrapped(options, vec![material], quad, |material, shift| {
+        material.position += shift.extend(0.)
+    });
+    (materials, spin)
+}
+
+/// Size and look of the black hole.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BlackHoleSettings {
+    /// Radius of the event horizon in screen pixels.
+    pub radius: Range<f32>,
+    /// Radius of the accretion disk, in horizon radii.
+    pub disk: Range<f32>,
+    /// How far the disk is tipped away from face on, in degrees.
+    pub tilt: Range<f32>,
+    /// How fast the inner edge of the disk turns, in radians per second.
+    pub spin: Range<f32>,
+}
+
+impl BlackHoleSettings {
+    pub const DEFAULT: BlackHoleSettings = BlackHoleSettings {
+        radius: 20.0..35.0,
+        disk: 2.5..3.5,
+        tilt: 70.0..82.0,
+        spin: 0.3..0.8,
+    };
+}
+
+impl Default for BlackHoleSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// World space covered by the black hole's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &BlackHoleMaterial) -> Rect {
+    Rect::from_center_size(
+        material.position.truncate(),
+        Vec2::splat(material.radius * 2.),
+    )
+}
+
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct BlackHoleMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: f32,
+    #[uniform(1)]
+    pub(crate) seed: f32,
+    #[uniform(2)]
+    #[animate]
+    pub(crate) time: f32,
+    /// Radius of the event horizon, as a fraction of the disk's.
+    #[uniform(3)]
+    pub(crate) horizon: f32,
+    /// Height of the disk over its width, `cos` of the tilt.
+    #[uniform(4)]
+    pub(crate) tilt: f32,
+    #[uniform(5)]
+    pub(crate) rotation: f32,
+
+    #[texture(6)]
+    #[sampler(7)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(8)]
+    pub(crate) position: Vec3,
+
+    /// Radius of the disk in world space, for the mesh.
+    pub(crate) radius: f32,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl ParallaxMaterial2D for BlackHoleMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
+    }
+}
+
+layers::blended_material!(BlackHoleMaterial, "black_hole");
+
+/// Turns the disk at a constant rate for as long as it runs, which no
+/// keyframes could loop: the shader divides the angle by the radius.
+#[derive(Component)]
+pub struct BlackHoleConfig {
+    time: f32,
+    /// Radians per second.
+    spin: f32,
+}
+
+impl BlackHoleConfig {
+    fn new(time: f32, spin: f32) -> Self {
+        BlackHoleConfig { time, spin }
+    }
+}
+
+impl AnimatedMaterialConfig for BlackHoleConfig {
+    fn advance(&mut self, delta: f32) {
+        // the disk comes back round every TAU, so time never grows past it
+        self.time = (self.time + self.spin * delta).rem_euclid(TAU);
+    }
+
+    fn values(&self) -> Vec<(&str, f32)> {
+        vec![("time", self.time)]
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use pixel_gen::*;
+use utils::screenspace::{CommonResolution, Space};
+
+const USAGE: &str = "\
+usage: pixel-gen render --output <PNG> [options]
+       pixel-gen export --output <GIF|PNG> [export options] [options]
+
+  --seed <N>             generation seed (random if omitted)
+  --colorscheme <NAME>   builtin (e.g. FunkyFutures) or a palette in assets/colorschemes
+  --pixels <N>           pixel resolution of the noise layers
+  --resolution <RES>     CommonResolution name or WIDTHxHEIGHT (default FourK16x9)
+  --no-nebulae, --no-dust, --no-stars, --no-planets,
+                         --no-asteroids, --no-galaxies
+  --black-hole, --tile, --darken, --transparency
+  --check-seams          render a tile and fail if it doesn't carry on into itself
+  --seam-tolerance <T>   how far apart (0-1) pixels across an edge may be (default 1/255)
+  --config <TOML>        start from a config file, flags override it
+  --assets <DIR>         directory holding stars-special.png
+
+export options, for a seamlessly looping animation:
+  --frames <N>           frames in the loop (default 48)
+  --seconds <S>          seconds the loop lasts, warning about any animation
+                         it speeds up or slows down much to fit (default 8)
+  --format <FORMAT>      gif, apng or sheet, a spritesheet PNG with a TOML file
+                         of its frames (default from the output's extension)
+  --columns <N>          frames per row of a sheet (default as square as fits)";
+
+/// What `render` and `export` draw and where it goes.
+struct SceneArgs {
+    options: Options,
+    output: PathBuf,
+    assets: PathBuf,
+}
+
+/// Parses the flags `render` and `export` share, handing any others to
+/// `other` along with the rest of `args`. `None` if `--help` was asked for.
+fn parse_scene<'a>(
+    args: &'a [String],
+    mut other: impl FnMut(&str, &mut std::slice::Iter<'a, String>) -> Result<(), String>,
+) -> Result<Option<SceneArgs>, String> {
+    let mut options = Options {
+        seed: GenerationSeed::random(),
+        ..Options::default()
+    };
+    let mut resolution = CommonResolution::FourK16x9.space();
+    let mut output = None;
+    let mut assets = headless::assets_dir();
+
+    if let Some(i) = args.iter().position(|arg| arg == "--config") {
+        let path = args.get(i + 1).ok_or(USAGE)?;
+        options =
+            Options::from_file(Path::new(path), options.seed).map_err(|err| err.to_string())?;
+    }
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => options.seed = GenerationSeed(parse(value(&mut args)?)?),
+            "--colorscheme" => options.colorscheme = parse(value(&mut args)?)?,
+            "--pixels" => options.pixels = parse(value(&mut args)?)?,
+            "--resolution" => resolution = parse_resolution(value(&mut args)?)?,
+            "--output" => output = Some(PathBuf::from(value(&mut args)?)),
+            "--assets" => assets = PathBuf::from(value(&mut args)?),
+            "--config" => _ = value(&mut args)?,
+            "--no-nebulae" => options.nebulae = false,
+            "--no-dust" => options.dust = false,
+            "--no-stars" => options.stars = false,
+            "--no-planets" => options.planets = false,
+            "--no-asteroids" => options.asteroids = false,
+            "--no-galaxies" => options.galaxies = false,
+            "--black-hole" => options.black_hole = true,
+            "--tile" => options.tile = true,
+            "--darken" => options.darken = true,
+            "--transparency" => options.transparency = true,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(None);
+            }
+            other_arg => other(other_arg, &mut args)?,
+        }
+    }
+
+    let output = output.ok_or(USAGE)?;
+    options.screen_size = ScreenSize {
+        screen_space: resolution,
+        show_ui: false,
+    };
+    Ok(Some(SceneArgs {
+        options,
+        output,
+        assets,
+    }))
+}
+
+/// `pixel-gen render ...`: writes one generated frame to a PNG.
+pub fn render(args: &[String]) -> Result<(), String> {
+    let mut check_seams = false;
+    let mut tolerance = SEAM_TOLERANCE;
+    let Some(SceneArgs {
+        mut options,
+        output,
+        assets,
+    }) = parse_scene(args, |arg, args| match arg {
+        "--check-seams" => {
+            check_seams = true;
+            Ok(())
+        }
+        "--seam-tolerance" => {
+            tolerance = parse(value(args)?)?;
+            Ok(())
+        }
+        other => Err(unexpected(other)),
+    })?
+    else {
+        return Ok(());
+    };
+
+    eprintln!("rendering seed {} to {}", options.seed, output.display());
+    if !check_seams {
+        return headless::render_to_file(&options, &assets, &output).map_err(|err| err.to_string());
+    }
+
+    options.tile = true;
+    let rimmed = headless::render_with_rim(&options, &assets).map_err(|err| err.to_string())?;
+    headless::write_png(&rimmed.without_rim(1), &output).map_err(|err| err.to_string())?;
+
+    let seams = find_seams(&rimmed, tolerance);
+    for seam in &seams {
+        eprintln!(
+            "seam between the {:?} edges: {} pixels differ, by up to {:.3}",
+            seam.edge, seam.mismatched, seam.largest
+        );
+    }
+    if seams.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} does not tile", output.display()))
+    }
+}
+
+/// `pixel-gen export ...`: writes a looping animation as a GIF, an APNG or a
+/// spritesheet.
+pub fn export(args: &[String]) -> Result<(), String> {
+    let mut export = export::Export::default();
+    let mut format = None;
+    // the default loop is a preview, too short for the slowest animations
+    let mut seconds_given = false;
+    let Some(SceneArgs {
+        options,
+        output,
+        assets,
+    }) = parse_scene(args, |arg, args| {
+        match arg {
+            "--frames" => export.frames = parse(value(args)?)?,
+            "--seconds" => {
+                export.seconds = parse(value(args)?)?;
+                seconds_given = true;
+            }
+            "--format" => format = Some(parse(value(args)?)?),
+            "--columns" => export.columns = Some(parse(value(args)?)?),
+            other => return Err(unexpected(other)),
+        }
+        Ok(())
+    })?
+    else {
+        return Ok(());
+    };
+
+    if export.frames == 0 || export.seconds <= 0. {
+        return Err("--frames and --seconds must be above 0".into());
+    }
+    export.format = format
+        .or_else(|| export::ExportFormat::from_path(&output))
+        .ok_or_else(|| format!("pass --format, {} is not a .gif or .png", output.display()))?;
+
+    eprintln!(
+        "exporting {} frames of seed {} to {}",
+        export.frames,
+        options.seed,
+        output.display()
+    );
+    let stretched =
+        export::export(&options, &assets, &export, &output).map_err(|err| err.to_string())?;
+    for stretch in stretched.iter().filter(|_| seconds_given) {
+        eprintln!(
+            "warning: the {} animation runs {:.1}x as fast as its own {:.1}s cycle to loop in {}s",
+            stretch.layer, stretch.speed, stretch.period, export.seconds
+        );
+    }
+    Ok(())
+}
+
+fn value<'a>(args: &mut std::slice::Iter<'a, String>) -> Result<&'a str, String> {
+    args.next().map(String::as_str).ok_or_else(|| USAGE.into())
+}
+
+fn unexpected(arg: &str) -> String {
+    format!("unexpected argument {arg}\n\n{USAGE}")
+}
+
+/// How far apart a channel of two pixels may be and still count as matching,
+/// for the rounding the noise does differently a tile over.
+const SEAM_TOLERANCE: f32 = 1. / 255.;
+
+fn parse<T: FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|err| format!("invalid value {value}: {err}"))
+}
+
+fn parse_resolution(value: &str) -> Result<Space, String> {
+    if let Some((width, height)) = value.split_once('x') {
+        if let (Ok(width), Ok(height)) = (width.parse::<u32>(), height.parse::<u32>()) {
+            return Ok(Space {
+                width: width as f32,
+                height: height as f32,
+            });
+        }
+    }
+
+    parse::<CommonResolution>(value).map(CommonResolution::space)
+}
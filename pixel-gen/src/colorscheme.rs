@@ -1,62 +1,376 @@
-use std::fmt::Display;
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    error::Error,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use bevy::{color::Color, prelude::Image};
+use bevy::{color::Color, prelude::*};
 use serde::Deserialize;
+use utils::colors::PaletteFormat;
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
-pub enum ColorScheme {
-    Borkfest,
-    Nyx8,
-    Ammo8,
-    FunkyFutures,
+/// Name of a palette registered in [`ColorSchemes`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(transparent)]
+pub struct ColorScheme(Cow<'static, str>);
+
+impl ColorScheme {
+    pub const BORKFEST: ColorScheme = ColorScheme::named("Borkfest");
+    pub const NYX8: ColorScheme = ColorScheme::named("Nyx8");
+    pub const AMMO8: ColorScheme = ColorScheme::named("Ammo8");
+    pub const FUNKY_FUTURES: ColorScheme = ColorScheme::named("FunkyFutures");
+
+    pub const fn named(name: &'static str) -> Self {
+        ColorScheme(Cow::Borrowed(name))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
 }
 
 impl Display for ColorScheme {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.0)
     }
 }
 
-impl ColorScheme {
-    pub fn next(&self) -> Self {
-        match self {
-            ColorScheme::Borkfest => ColorScheme::Nyx8,
-            ColorScheme::Nyx8 => ColorScheme::Ammo8,
-            ColorScheme::Ammo8 => ColorScheme::FunkyFutures,
-            ColorScheme::FunkyFutures => ColorScheme::Borkfest,
+impl From<String> for ColorScheme {
+    fn from(name: String) -> Self {
+        ColorScheme(Cow::Owned(name))
+    }
+}
+
+impl FromStr for ColorScheme {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.to_string().into())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+
+impl Palette {
+    /// The first color fills the background, the rest make up the gradient,
+    /// so a palette needs at least three.
+    pub const MIN_COLORS: usize = 3;
+
+    /// How many colors the built in palettes have: the background and the 8
+    /// gradient bands the shaders step through.
+    pub const COLORS: usize = 9;
+
+    /// A palette of `colors`, kept as they are if there are enough. With fewer,
+    /// the first still fills the background and all of them are spread over
+    /// the gradient, blending new ones in only where it has more bands than
+    /// there are colors.
+    pub fn new(colors: Vec<Color>) -> Option<Self> {
+        if colors.len() < Self::MIN_COLORS {
+            return None;
+        }
+        if colors.len() >= Self::COLORS {
+            return Some(Palette { colors });
         }
+
+        let gradient = utils::colors::resample(&colors, Self::COLORS - 1);
+        Some(Palette {
+            colors: std::iter::once(colors[0]).chain(gradient).collect(),
+        })
     }
 
-    pub fn colors(&self) -> [&'static str; 9] {
-        match self {
-            ColorScheme::Borkfest => [
-                "#171711", "#202215", "#3a2802", "#963c3c", "#ca5a2e", "#ff7831", "#f39949",
-                "#ebc275", "#dfd785",
-            ],
-            ColorScheme::Nyx8 => [
-                "#01090f", "#08141e", "#0f2a3f", "#20394f", "#4e495f", "#816271", "#997577",
-                "#c3a38a", "#f6d6bd",
-            ],
-            ColorScheme::Ammo8 => [
-                "#000a03", "#040c06", "#112318", "#1e3a29", "#305d42", "#4d8061", "#89a257",
-                "#bedc7f", "#eeffcc",
-            ],
-            ColorScheme::FunkyFutures => [
-                "#120826", "#2b0f54", "#ab1f65", "#ff4f69", "#ff8142", "#ffda45", "#3368dc",
-                "#49e7ec", "#fff7f8",
-            ],
+    fn from_hex(hex_codes: &[&str]) -> Self {
+        Palette {
+            colors: hex_codes
+                .iter()
+                .map(|hex| utils::colors::hex_to_color(hex))
+                .collect(),
         }
     }
 
+    /// Every distinct opaque color of the image, in pixel order.
+    pub fn from_image(image: &image::RgbaImage) -> Option<Self> {
+        let mut seen = Vec::new();
+        for pixel in image.pixels().filter(|pixel| pixel[3] == u8::MAX) {
+            if !seen.contains(pixel) {
+                seen.push(*pixel);
+            }
+        }
+
+        Palette::new(
+            seen.into_iter()
+                .map(|pixel| Color::srgb_u8(pixel[0], pixel[1], pixel[2]))
+                .collect(),
+        )
+    }
+
+    /// Reads a palette file, or every distinct color of a palette image.
+    pub fn load(path: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        match PaletteFormat::from_path(path) {
+            Some(format) => Ok(Palette::new(format.parse(&fs::read_to_string(path)?)?)),
+            None => Ok(Palette::from_image(&image::open(path)?.to_rgba8())),
+        }
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
     pub fn bg_color(&self) -> Color {
-        utils::colors::hex_to_color(self.colors()[1])
+        self.colors[1]
     }
 
     pub fn gradient_image_with_bg(&self) -> (Image, Color) {
-        let colors = self.colors();
         (
-            utils::colors::color_gradiant(&colors[1..], 100),
-            utils::colors::hex_to_color(colors[1]),
+            utils::colors::gradient(&self.colors[1..], 100),
+            self.colors[1],
         )
     }
 }
+
+/// Every palette a [`ColorScheme`] can name, in the order `C` cycles through them.
+#[derive(Resource)]
+pub struct ColorSchemes {
+    schemes: Vec<(ColorScheme, Palette)>,
+}
+
+impl Default for ColorSchemes {
+    fn default() -> Self {
+        ColorSchemes {
+            schemes: vec![
+                (
+                    ColorScheme::BORKFEST,
+                    Palette::from_hex(&[
+                        "#171711", "#202215", "#3a2802", "#963c3c", "#ca5a2e", "#ff7831",
+                        "#f39949", "#ebc275", "#dfd785",
+                    ]),
+                ),
+                (
+                    ColorScheme::NYX8,
+                    Palette::from_hex(&[
+                        "#01090f", "#08141e", "#0f2a3f", "#20394f", "#4e495f", "#816271",
+                        "#997577", "#c3a38a", "#f6d6bd",
+                    ]),
+                ),
+                (
+                    ColorScheme::AMMO8,
+                    Palette::from_hex(&[
+                        "#000a03", "#040c06", "#112318", "#1e3a29", "#305d42", "#4d8061",
+                        "#89a257", "#bedc7f", "#eeffcc",
+                    ]),
+                ),
+                (
+                    ColorScheme::FUNKY_FUTURES,
+                    Palette::from_hex(&[
+                        "#120826", "#2b0f54", "#ab1f65", "#ff4f69", "#ff8142", "#ffda45",
+                        "#3368dc", "#49e7ec", "#fff7f8",
+                    ]),
+                ),
+            ],
+        }
+    }
+}
+
+impl ColorSchemes {
+    /// Adds a palette, replacing any already registered under the same name.
+    pub fn register(&mut self, scheme: ColorScheme, palette: Palette) {
+        match self.schemes.iter_mut().find(|(name, _)| *name == scheme) {
+            Some((_, existing)) => *existing = palette,
+            None => self.schemes.push((scheme, palette)),
+        }
+    }
+
+    /// The palette `scheme` names, exactly or as a palette file would.
+    pub fn get(&self, scheme: &ColorScheme) -> Option<&Palette> {
+        let scheme = self
+            .names()
+            .find(|name| *name == scheme)
+            .or_else(|| self.matching(scheme))?;
+        self.schemes
+            .iter()
+            .find(|(name, _)| name == scheme)
+            .map(|(_, palette)| palette)
+    }
+
+    /// Like [`ColorSchemes::get`], but falls back to the first palette.
+    pub fn palette(&self, scheme: &ColorScheme) -> &Palette {
+        self.get(scheme).unwrap_or_else(|| {
+            warn!("unknown colorscheme {scheme}");
+            &self.schemes[0].1
+        })
+    }
+
+    pub fn next(&self, scheme: &ColorScheme) -> ColorScheme {
+        let index = self
+            .schemes
+            .iter()
+            .position(|(name, _)| name == scheme)
+            .map_or(0, |i| (i + 1) % self.schemes.len());
+        self.schemes[index].0.clone()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &ColorScheme> {
+        self.schemes.iter().map(|(name, _)| name)
+    }
+
+    /// The scheme a palette file called `stem` is registered as: one already
+    /// registered that it names, which it replaces, or else a new one named
+    /// `stem`. Names match ignoring case, punctuation and a size suffix like
+    /// Lospec's `-1x`, so `nyx8.png` and `borkfest-100.png` replace the built
+    /// in "Nyx8" and "Borkfest" rather than listing them twice.
+    fn scheme_for(&self, stem: &str) -> ColorScheme {
+        let scheme = ColorScheme::from(stem.to_string());
+        self.matching(&scheme).cloned().unwrap_or(scheme)
+    }
+
+    /// The registered scheme `scheme` names, see [`ColorSchemes::scheme_for`].
+    fn matching(&self, scheme: &ColorScheme) -> Option<&ColorScheme> {
+        let key = scheme_key(scheme.name());
+        self.names().find(|name| scheme_key(name.name()) == key)
+    }
+
+    /// Registers every palette image or palette file (see [`PaletteFormat`])
+    /// in `dir`, named after its file stem, see [`ColorSchemes::scheme_for`].
+    /// Returns how many were loaded.
+    pub fn load_dir(&mut self, dir: &Path) -> io::Result<usize> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                PaletteFormat::from_path(path).is_some()
+                    || image::ImageFormat::from_path(path)
+                        .is_ok_and(|format| format.reading_enabled())
+            })
+            .collect();
+        paths.sort();
+
+        let mut loaded = 0;
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+
+            let palette = match Palette::load(&path) {
+                Ok(palette) => palette,
+                Err(err) => {
+                    warn!("could not load palette {}: {err}", path.display());
+                    continue;
+                }
+            };
+            let Some(palette) = palette else {
+                warn!(
+                    "palette {} needs at least {} colors",
+                    path.display(),
+                    Palette::MIN_COLORS
+                );
+                continue;
+            };
+
+            self.register(self.scheme_for(name), palette);
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+}
+
+/// `name` lowercased without punctuation or a size suffix like Lospec's `-1x`.
+fn scheme_key(name: &str) -> String {
+    let is_size = |size: &str| {
+        let digits = size.strip_suffix('x').unwrap_or(size);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let name = match name.rsplit_once('-') {
+        Some((base, size)) if is_size(size) => base,
+        _ => name,
+    };
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Directory user palettes are loaded from on startup.
+#[derive(Resource)]
+pub struct ColorSchemeDir(pub PathBuf);
+
+impl Default for ColorSchemeDir {
+    fn default() -> Self {
+        ColorSchemeDir(crate::headless::assets_dir().join("colorschemes"))
+    }
+}
+
+pub fn load_color_schemes(dir: Res<ColorSchemeDir>, mut schemes: ResMut<ColorSchemes>) {
+    match schemes.load_dir(&dir.0) {
+        Ok(loaded) => info!("loaded {loaded} palettes from {}", dir.0.display()),
+        Err(err) => warn!("could not read palette dir {}: {err}", dir.0.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(colors: &[Color]) -> Vec<String> {
+        colors
+            .iter()
+            .map(|color| color.to_srgba().to_hex().to_lowercase())
+            .collect()
+    }
+
+    fn from_hex(codes: &[&str]) -> Vec<Color> {
+        codes
+            .iter()
+            .map(|code| utils::colors::hex_to_color(code))
+            .collect()
+    }
+
+    #[test]
+    fn eight_colors_keep_their_background_and_gain_none() {
+        let codes = [
+            "#0d2b45", "#203c56", "#544e68", "#8d697a", "#d08159", "#ffaa5e", "#ffd4a3", "#ffecd6",
+        ];
+        let palette = Palette::new(from_hex(&codes)).unwrap();
+        assert_eq!(hex(&palette.colors()[..1]), ["#0d2b45"]);
+        assert_eq!(hex(&palette.colors()[1..]), codes);
+        assert_eq!(hex(&[palette.bg_color()]), ["#0d2b45"]);
+    }
+
+    #[test]
+    fn enough_colors_are_kept_as_they_are() {
+        let codes = [
+            "#000000", "#111111", "#222222", "#333333", "#444444", "#555555", "#666666", "#777777",
+            "#888888", "#999999",
+        ];
+        let palette = Palette::new(from_hex(&codes)).unwrap();
+        assert_eq!(hex(palette.colors()), codes);
+    }
+
+    #[test]
+    fn palette_files_replace_the_builtins_they_name() {
+        let mut schemes = ColorSchemes::default();
+        assert_eq!(schemes.scheme_for("nyx8"), ColorScheme::NYX8);
+        assert_eq!(schemes.scheme_for("borkfest-100"), ColorScheme::BORKFEST);
+        assert_eq!(
+            schemes.scheme_for("funky-futures-8x"),
+            ColorScheme::FUNKY_FUTURES
+        );
+        assert_eq!(schemes.scheme_for("slso8"), ColorScheme::named("slso8"));
+
+        let before = schemes.names().count();
+        schemes
+            .load_dir(&crate::headless::assets_dir().join("colorschemes"))
+            .unwrap();
+        let names: Vec<&str> = schemes.names().map(ColorScheme::name).collect();
+        assert_eq!(names.len(), before + 1, "{names:?}");
+        assert!(names.contains(&"slso8"));
+        assert_eq!(
+            schemes.get(&"nyx8".parse().unwrap()),
+            schemes.get(&ColorScheme::NYX8)
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::ops::Range;
+
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::*;
+
+/// A comet or shooting star crossing the frame.
+#[derive(Component)]
+pub struct Comet {
+    /// Where it is in the scene, before parallax scrolls it.
+    centre: Vec2,
+    /// How far its layer had scrolled when it appeared, so it appears where
+    /// it was placed and drifts from there.
+    scrolled: Vec2,
+    velocity: Vec2,
+    /// Seconds left before it is gone; shooting stars fade out over the last
+    /// [`FADE_SECONDS`] of theirs.
+    life: f32,
+    fades: bool,
+}
+
+const FADE_SECONDS: f32 = 0.3;
+
+/// The `position` uniform's z; the mesh's own z sorts the comet.
+const COMET_Z: f32 = 1.6;
+
+/// Where new comets are drawn from, reset with the rest of the scene.
+#[derive(Resource)]
+pub struct Comets {
+    rng: SeedRng,
+    color_gradiant: Option<Handle<Image>>,
+}
+
+impl Default for Comets {
+    fn default() -> Self {
+        Comets {
+            rng: GenerationSeed(0).stream("comets"),
+            color_gradiant: None,
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct CometSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    mats: ResMut<'w, Assets<CometMaterial>>,
+    comets: ResMut<'w, Comets>,
+    time: Res<'w, Time>,
+    options: Res<'w, config::Options>,
+    parallax: Res<'w, Parallax>,
+}
+
+#[derive(SystemParam)]
+pub struct CometReset<'w> {
+    comets: ResMut<'w, Comets>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+/// Regenerating clears every comet in flight and restarts the stream new
+/// ones are drawn from; they are spawned over time by [`spawn_comets`].
+impl BackgroundLayer for Comet {
+    const NAME: &'static str = "comets";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyK);
+
+    type Param = CometReset<'static>;
+
+    fn spawn(_: &mut Commands, options: &config::ResolvedOptions, cr: &mut CometReset) {
+        cr.comets.rng = Self::rng(options);
+        cr.comets.color_gradiant =
+            Some(cr.images.add(options.palette().gradient_image_with_bg().0));
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.comets
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.comets = enabled;
+    }
+}
+
+pub fn spawn_comets(mut sc: CometSpawner) {
+    if !sc.options.animate || !sc.options.comets {
+        return;
+    }
+    let Some(color_gradiant) = sc.comets.color_gradiant.clone() else {
+        return;
+    };
+
+    let settings = &sc.options.comet_settings;
+    let minutes = sc.time.delta_seconds() / 60.;
+    let rng = &mut sc.comets.rng;
+
+    let mut spawned = Vec::new();
+    if rng.gen::<f32>() < settings.comets * minutes {
+        spawned.push(comet(&sc.options, rng, color_gradiant.clone()));
+    }
+    if rng.gen::<f32>() < settings.shooting_stars * minutes {
+        spawned.push(shooting_star(&sc.options, rng, color_gradiant));
+    }
+
+    for (mut material, mut comet) in spawned {
+        comet.scrolled = scroll(&sc.options, &sc.parallax);
+        material.position = comet.position(&sc.options, &sc.parallax);
+        let mesh = MaterialMesh2dBundle {
+            mesh: sc.meshes.add(Rectangle::from_size(material.size)).into(),
+            material: sc.mats.add(material),
+            // by default passes over the asteroids, behind the black hole and planets
+            transform: Transform::from_translation(Vec3::ZERO.with_z(sc.options.layer_z.comets)),
+            ..default()
+        };
+
+        sc.commands.spawn((mesh, comet));
+    }
+}
+
+pub fn move_comets(
+    time: Res<Time>,
+    options: Res<config::Options>,
+    parallax: Res<Parallax>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Comet, &Handle<CometMaterial>)>,
+    mut materials: ResMut<Assets<CometMaterial>>,
+) {
+    if !options.animate {
+        return;
+    }
+
+    for (entity, mut comet, handle) in query.iter_mut() {
+        comet.life -= time.delta_seconds();
+        if comet.life <= 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let Some(material) = materials.get_mut(handle.id()) else {
+            continue;
+        };
+        let velocity = comet.velocity;
+        comet.centre += velocity * time.delta_seconds();
+        material.position = comet.position(&options, &parallax);
+        if comet.fades {
+            material.fade = (comet.life / FADE_SECONDS).min(1.);
+        }
+    }
+}
+
+/// How far the comets' layer has scrolled, in screen pixels.
+fn scroll(options: &config::Options, parallax: &Parallax) -> Vec2 {
+    if options.parallax {
+        parallax.offset() * options.parallax_settings.comets
+    } else {
+        Vec2::ZERO
+    }
+}
+
+impl Comet {
+    /// Where the comet is drawn, having scrolled with its layer since it
+    /// appeared.
+    fn position(&self, options: &config::Options, parallax: &Parallax) -> Vec3 {
+        let scrolled = scroll(options, parallax) - self.scrolled;
+        let anchor = if options.parallax {
+            parallax.anchor()
+        } else {
+            Vec2::ZERO
+        };
+        (self.centre + anchor - scrolled).extend(COMET_Z)
+    }
+}
+
+/// A slow comet that enters on one side of the frame and leaves on the other.
+fn comet(
+    options: &config::Options,
+    rng: &mut impl Rng,
+    color_gradiant: Handle<Image>,
+) -> (CometMaterial, Comet) {
+    let settings = &options.comet_settings;
+    let direction = direction(settings, rng);
+    let size = Vec2::new(
+        config::sample(rng, &settings.comet_length),
+        config::sample(rng, &settings.comet_width),
+    );
+    let speed = config::sample(rng, &settings.comet_speed).max(1.);
+
+    // start far enough back that it is off screen wherever it crosses, even
+    // from one corner of the frame to the other
+    let reach = options.screen_size.height() * 3. + size.x;
+    let centre = options.screen_size.random_postion(rng, 0.).truncate();
+    let material = CometMaterial::new(
+        options,
+        size,
+        true,
+        centre - direction * reach / 2.,
+        direction,
+        color_gradiant,
+    );
+    let comet = Comet {
+        centre: material.position.truncate(),
+        scrolled: Vec2::ZERO,
+        velocity: direction * speed,
+        life: reach / speed,
+        fades: false,
+    };
+
+    (material, comet)
+}
+
+/// A quick streak that appears anywhere and fades out before it gets far.
+fn shooting_star(
+    options: &config::Options,
+    rng: &mut impl Rng,
+    color_gradiant: Handle<Image>,
+) -> (CometMaterial, Comet) {
+    let settings = &options.comet_settings;
+    let direction = direction(settings, rng);
+    let size = Vec2::new(config::sample(rng, &settings.shooting_star_length), 2.);
+    let speed = config::sample(rng, &settings.shooting_star_speed);
+
+    let position = options.screen_size.random_postion(rng, 0.).truncate();
+    let material = CometMaterial::new(options, size, false, position, direction, color_gradiant);
+    let comet = Comet {
+        centre: position,
+        scrolled: Vec2::ZERO,
+        velocity: direction * speed,
+        life: config::sample(rng, &settings.shooting_star_duration),
+        fades: true,
+    };
+
+    (material, comet)
+}
+
+fn direction(settings: &CometSettings, rng: &mut impl Rng) -> Vec2 {
+    Vec2::from_angle(config::sample(rng, &settings.direction).to_radians())
+}
+
+/// How often comets and shooting stars turn up and what they look like.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CometSettings {
+    /// Average number of comets per minute.
+    pub comets: f32,
+    /// Average number of shooting stars per minute.
+    pub shooting_stars: f32,
+    /// Direction of travel in degrees, counterclockwise from moving right.
+    pub direction: Range<f32>,
+    /// Bands of the color gradient, from the end of the tail to the head.
+    pub colors: Range<u32>,
+    /// In screen pixels per second.
+    pub comet_speed: Range<f32>,
+    pub comet_length: Range<f32>,
+    pub comet_width: Range<f32>,
+    /// In screen pixels per second.
+    pub shooting_star_speed: Range<f32>,
+    pub shooting_star_length: Range<f32>,
+    /// In seconds.
+    pub shooting_star_duration: Range<f32>,
+}
+
+impl CometSettings {
+    pub const DEFAULT: CometSettings = CometSettings {
+        comets: 1.,
+        shooting_stars: 6.,
+        direction: 200.0..250.0,
+        colors: 3..8,
+        comet_speed: 20.0..50.0,
+        comet_length: 60.0..140.0,
+        comet_width: 6.0..12.0,
+        shooting_star_speed: 400.0..800.0,
+        shooting_star_length: 30.0..80.0,
+        shooting_star_duration: 0.4..1.0,
+    };
+}
+
+impl Default for CometSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct CometMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: Vec2,
+    #[uniform(1)]
+    pub(crate) head: i32,
+    #[uniform(2)]
+    pub(crate) colors: Vec2,
+    #[uniform(3)]
+    pub(crate) fade: f32,
+
+    #[texture(4)]
+    #[sampler(5)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(6)]
+    pub(crate) position: Vec3,
+    #[uniform(7)]
+    pub(crate) direction: Vec2,
+
+    /// Length and width in world space, for the mesh.
+    pub(crate) size: Vec2,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl CometMaterial {
+    fn new(
+        options: &config::Options,
+        size: Vec2,
+        head: bool,
+        position: Vec2,
+        direction: Vec2,
+        color_gradiant: Handle<Image>,
+    ) -> Self {
+        let settings = &options.comet_settings;
+        let last = settings
+            .colors
+            .end
+            .saturating_sub(1)
+            .max(settings.colors.start);
+        CometMaterial {
+            // two screen pixels per pixel, like the big stars
+            pixels: (size / 2.).max(Vec2::ONE).floor(),
+            head: head as i32,
+            colors: Vec2::new(settings.colors.start as f32, last as f32),
+            fade: 1.,
+            color_texture: Some(color_gradiant),
+            position: position.extend(COMET_Z),
+            direction,
+            size,
+            blend: options.blend_modes.comets,
+        }
+    }
+}
+
+layers::blended_material!(CometMaterial, "comets");
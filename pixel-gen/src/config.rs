@@ -1,74 +1,169 @@
-use std::fs;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    ops::{Deref, Range},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use bevy::prelude::*;
-use rand::Rng;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::{distributions::uniform::SampleUniform, Rng};
 use serde::Deserialize;
-use toml::Value;
 use utils::screenspace::Space;
 
-use crate::{colorscheme::ColorScheme, RefreshAllEvent};
+use crate::{
+    asteroids::AsteroidSettings,
+    black_hole::BlackHoleSettings,
+    colorscheme::{ColorScheme, ColorSchemes, Palette},
+    comets::CometSettings,
+    galaxies::GalaxySettings,
+    layers::{self, BlendModes, LayerRegistry, LayerZ},
+    nebulae::NebulaeSettings,
+    parallax::ParallaxSettings,
+    planets::PlanetSettings,
+    seed::GenerationSeed,
+    star_stuff::DustSettings,
+    stars::StarSettings,
+    RefreshAllEvent,
+};
 
-#[derive(Resource, Clone, Copy, Deserialize, PartialEq)]
+#[derive(Resource, Clone, Deserialize, PartialEq)]
+#[serde(default)]
 pub struct Options {
+    pub seed: GenerationSeed,
     pub pixels: f32,
     pub colorscheme: ColorScheme,
     pub stars: bool,
     pub dust: bool,
     pub nebulae: bool,
     pub planets: bool,
+    pub asteroids: bool,
+    pub galaxies: bool,
+    pub black_hole: bool,
+    pub comets: bool,
+    /// Scroll the layers at different speeds, for a live game background.
+    pub parallax: bool,
     pub tile: bool,
     pub darken: bool,
-    //NOTE: Currenty does nothing
+    /// Skip the background fill and fade the nebulae and dust out, so the
+    /// layers can be composited over other art.
     pub transparency: bool,
     pub animate: bool,
     pub screen_size: ScreenSize,
+    pub nebulae_settings: NebulaeSettings,
+    pub dust_settings: DustSettings,
+    pub planet_settings: PlanetSettings,
+    pub star_settings: StarSettings,
+    pub asteroid_settings: AsteroidSettings,
+    pub galaxy_settings: GalaxySettings,
+    pub black_hole_settings: BlackHoleSettings,
+    pub comet_settings: CometSettings,
+    pub parallax_settings: ParallaxSettings,
+    pub layer_z: LayerZ,
+    pub blend_modes: BlendModes,
+    /// Toggles of layers other crates add, by name.
+    pub layers: BTreeMap<String, bool>,
 }
 
 pub const DEFAULT_OPTIONS: Options = Options {
+    seed: GenerationSeed(0),
     pixels: 200.0,
-    colorscheme: ColorScheme::FunkyFutures,
+    colorscheme: ColorScheme::FUNKY_FUTURES,
     stars: true,
     dust: true,
     nebulae: true,
     planets: true,
+    asteroids: true,
+    galaxies: true,
+    black_hole: false,
+    comets: true,
+    parallax: false,
     tile: false,
     darken: false,
     transparency: false,
     animate: false,
     screen_size: ScreenSize::new(),
+    nebulae_settings: NebulaeSettings::DEFAULT,
+    dust_settings: DustSettings::DEFAULT,
+    planet_settings: PlanetSettings::DEFAULT,
+    star_settings: StarSettings::DEFAULT,
+    asteroid_settings: AsteroidSettings::DEFAULT,
+    galaxy_settings: GalaxySettings::DEFAULT,
+    black_hole_settings: BlackHoleSettings::DEFAULT,
+    comet_settings: CometSettings::DEFAULT,
+    parallax_settings: ParallaxSettings::DEFAULT,
+    layer_z: LayerZ::DEFAULT,
+    blend_modes: BlendModes::DEFAULT,
+    layers: BTreeMap::new(),
 };
 
+impl Default for Options {
+    fn default() -> Self {
+        DEFAULT_OPTIONS
+    }
+}
+
+/// `Options` together with the registries the names in it refer to.
+#[derive(SystemParam)]
+pub struct ResolvedOptions<'w> {
+    options: Res<'w, Options>,
+    color_schemes: Res<'w, ColorSchemes>,
+}
+
+impl ResolvedOptions<'_> {
+    pub fn palette(&self) -> &Palette {
+        self.color_schemes.palette(&self.options.colorscheme)
+    }
+}
+
+impl Deref for ResolvedOptions<'_> {
+    type Target = Options;
+
+    fn deref(&self) -> &Self::Target {
+        &self.options
+    }
+}
+
 pub fn change_options(
     mut options: ResMut<Options>,
+    color_schemes: Res<ColorSchemes>,
+    layers: Res<LayerRegistry>,
     kb_input: Res<ButtonInput<KeyCode>>,
     mut refresh_all: EventWriter<RefreshAllEvent>,
 ) {
     if kb_input.just_pressed(KeyCode::KeyC) {
-        //TODO: set colorscheme
-        options.colorscheme = options.colorscheme.next();
+        options.colorscheme = color_schemes.next(&options.colorscheme);
     } else if kb_input.just_pressed(KeyCode::KeyT) {
         options.tile = !options.tile;
-    } else if kb_input.just_pressed(KeyCode::KeyD) {
-        options.dust = !options.dust;
     } else if kb_input.just_pressed(KeyCode::KeyA) {
         options.transparency = !options.transparency;
-    } else if kb_input.just_pressed(KeyCode::KeyS) {
-        options.stars = !options.stars;
-    } else if kb_input.just_pressed(KeyCode::KeyN) {
-        options.nebulae = !options.nebulae;
     } else if kb_input.just_pressed(KeyCode::KeyW) {
         options.darken = !options.darken;
-    } else if kb_input.just_pressed(KeyCode::KeyP) {
-        options.planets = !options.planets;
+    } else if kb_input.just_pressed(KeyCode::KeyX) {
+        options.parallax = !options.parallax;
     } else if kb_input.just_pressed(KeyCode::KeyM) {
         options.animate = !options.animate;
-    } else {
+    } else if !layers::toggle_pressed(&layers, &mut options, &kb_input) {
         return;
     }
 
     refresh_all.send(RefreshAllEvent);
 }
 
+/// Draws from a range given in the config, which may be empty when someone
+/// pins a value with `[n, n]`; that yields `n` rather than panicking.
+pub(crate) fn sample<T: SampleUniform + PartialOrd + Copy>(
+    rng: &mut impl Rng,
+    range: &Range<T>,
+) -> T {
+    if range.is_empty() {
+        range.start
+    } else {
+        rng.gen_range(range.clone())
+    }
+}
+
 pub fn update_screen_size(query: Query<&Window>, mut options: ResMut<Options>) {
     let Ok(window) = query.get_single() else {
         return;
@@ -135,29 +230,296 @@ impl ScreenSize {
         }
     }
 
-    pub fn random_postion(&self, z: f32) -> Vec3 {
-        if self.screen_space.width == 0. && self.screen_space.height == 0. {
+    /// World space the scene is drawn in, beside the ui if it is shown.
+    pub fn rect(&self) -> Rect {
+        Rect::from_center_size(
+            Vec2::new(self.x_offset(), 0.),
+            Vec2::new(self.width(), self.height()),
+        )
+    }
+
+    /// A random point anywhere in `rect`, at depth `z`.
+    pub fn random_postion(&self, rng: &mut impl Rng, z: f32) -> Vec3 {
+        let rect = self.rect();
+        if rect.is_empty() {
             return Vec3::ZERO;
         }
-        let half_square = self.screen_space.height / 2.;
-        let x_offset = self.x_offset();
-        let mut rng = rand::thread_rng();
-        let x = rng.gen_range((x_offset - half_square)..(x_offset + half_square));
-        let y = rng.gen_range(-half_square..half_square);
+        let x = rng.gen_range(rect.min.x..rect.max.x);
+        let y = rng.gen_range(rect.min.y..rect.max.y);
         Vec3::new(x, y, z)
     }
 }
 
-fn refresh_options_from_file(mut old_options: ResMut<Options>) {
-    let Ok(data) = fs::read_to_string("config.toml") else {
-        warn!("could not read config file");
-        return;
+/// The TOML file `Options` are read from, polled for changes while the app runs.
+#[derive(Resource)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    last_modified: Option<SystemTime>,
+    poll: Timer,
+}
+
+impl ConfigFile {
+    const POLL_SECONDS: f32 = 0.5;
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        ConfigFile {
+            path: path.into(),
+            last_modified: None,
+            poll: Timer::from_seconds(Self::POLL_SECONDS, TimerMode::Repeating),
+        }
+    }
+
+    /// Returns true the first time a new modification of the file is seen.
+    fn changed(&mut self) -> bool {
+        match fs::metadata(&self.path).and_then(|meta| meta.modified()) {
+            Ok(modified) if self.last_modified != Some(modified) => {
+                self.last_modified = Some(modified);
+                true
+            }
+            Ok(_) => false,
+            Err(err) => {
+                if self.last_modified.take().is_some() {
+                    warn!("lost config file {}: {err}", self.path.display());
+                }
+                false
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        line: Option<(usize, String)>,
+        field: Option<String>,
+        message: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => {
+                write!(f, "could not read config file {}: {source}", path.display())
+            }
+            ConfigError::Parse {
+                path,
+                line,
+                field,
+                message,
+            } => {
+                write!(f, "could not parse config file {}", path.display())?;
+                if let Some((number, _)) = line {
+                    write!(f, " at line {number}")?;
+                }
+                if let Some(field) = field {
+                    write!(f, " (field `{field}`)")?;
+                }
+                write!(f, ": {}", message.trim_end())?;
+                if let Some((number, text)) = line {
+                    write!(f, "\n{number:>5} | {text}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The `seed` key on its own, to tell a config without one apart from one
+/// that sets the default.
+#[derive(Deserialize)]
+struct FileSeed {
+    seed: Option<GenerationSeed>,
+}
+
+impl Options {
+    /// Loads options from a TOML file, keeping `seed` when the file sets none.
+    pub fn from_file(path: &Path, seed: GenerationSeed) -> Result<Options, ConfigError> {
+        let data = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut options: Options = toml::from_str(&data).map_err(|err: toml::de::Error| {
+            let line = err
+                .span()
+                .map(|span| line_at(&data, span.start))
+                .filter(|(_, text)| !text.trim().is_empty());
+            let field = err.span().and_then(|span| field_at(&data, span.start));
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                line,
+                field,
+                message: err.message().to_string(),
+            }
+        })?;
+
+        options.seed = toml::from_str::<FileSeed>(&data)
+            .ok()
+            .and_then(|file| file.seed)
+            .unwrap_or(seed);
+        Ok(options)
+    }
+}
+
+/// 1-based line number and text of the line containing `offset`.
+fn line_at(data: &str, offset: usize) -> (usize, String) {
+    let offset = offset.min(data.len());
+    let start = data[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let end = data[offset..].find('\n').map_or(data.len(), |i| offset + i);
+    let number = data[..offset].matches('\n').count() + 1;
+    (number, data[start..end].trim_end().to_string())
+}
+
+/// Dotted key of the `key = value` pair containing `offset`, including its table header.
+fn field_at(data: &str, offset: usize) -> Option<String> {
+    let offset = offset.min(data.len());
+    let start = data[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let (_, text) = line_at(data, offset);
+    let key = text.split_once('=')?.0.trim().trim_matches('"');
+
+    let table = data[..start]
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| line.starts_with('['))
+        .map(|header| header.trim_matches(|c| c == '[' || c == ']').trim());
+
+    Some(match table {
+        Some(table) => format!("{table}.{key}"),
+        None => key.to_string(),
+    })
+}
+
+/// Applies the config file to `old_options`, keeping the measured screen size.
+/// Returns true if the options changed.
+fn refresh_options_from_file(path: &Path, old_options: &mut ResMut<Options>) -> bool {
+    let mut options = match Options::from_file(path, old_options.seed) {
+        Ok(options) => options,
+        Err(err) => {
+            error!("{err}");
+            return false;
+        }
     };
 
-    let Ok(options): Result<Options, _> = toml::from_str(&data) else {
-        warn!("could not parse config file");
+    options.screen_size.screen_space = old_options.screen_size.screen_space;
+    old_options.set_if_neq(options)
+}
+
+pub fn load_config_file(mut file: ResMut<ConfigFile>, mut options: ResMut<Options>) {
+    if file.changed() {
+        refresh_options_from_file(&file.path, &mut options);
+    } else {
+        warn!("could not find config file {}", file.path.display());
+    }
+}
+
+pub fn watch_config_file(
+    time: Res<Time>,
+    mut file: ResMut<ConfigFile>,
+    mut options: ResMut<Options>,
+    mut refresh_all: EventWriter<RefreshAllEvent>,
+) {
+    if !file.poll.tick(time.delta()).just_finished() || !file.changed() {
         return;
-    };
+    }
+
+    info!("reloading config file {}", file.path.display());
+    if refresh_options_from_file(&file.path, &mut options) {
+        refresh_all.send(RefreshAllEvent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
 
-    old_options.set_if_neq(options);
+    /// Writes `data` to a config file of its own for the test called `name`.
+    fn write(name: &str, data: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pixel-gen-{name}.toml"));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn load(name: &str, data: &str, seed: GenerationSeed) -> Result<Options, ConfigError> {
+        let path = write(name, data);
+        let options = Options::from_file(&path, seed);
+        fs::remove_file(&path).unwrap();
+        options
+    }
+
+    #[test]
+    fn seedless_config_keeps_seed() {
+        let options = load(
+            "seedless",
+            "stars = false\n[star_settings]\nframe_swaps = 0.5\n",
+            GenerationSeed(1234),
+        )
+        .unwrap();
+        assert_eq!(options.seed, GenerationSeed(1234));
+        assert_eq!(options.star_settings.frame_swaps, 0.5);
+    }
+
+    #[test]
+    fn config_seed_wins() {
+        let options = load("seeded", "seed = 7\n", GenerationSeed(1234)).unwrap();
+        assert_eq!(options.seed, GenerationSeed(7));
+    }
+
+    #[test]
+    fn parse_error_names_line_and_field() {
+        let data = "stars = true\n\n[planet_settings.weights]\nterran = 2.0\nice = \"lots\"\n";
+        let message = load("bad-type", data, GenerationSeed(1))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains(" at line 5 "), "{message}");
+        assert!(
+            message.contains("(field `planet_settings.weights.ice`)"),
+            "{message}"
+        );
+        assert!(message.ends_with("    5 | ice = \"lots\""), "{message}");
+    }
+
+    #[test]
+    fn unknown_variant_names_line_and_field() {
+        let data = "[blend_modes]\nnebulae = \"screen\"\ndust = \"glow\"\n";
+        let message = load("bad-variant", data, GenerationSeed(1))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.contains(" at line 3 "), "{message}");
+        assert!(message.contains("(field `blend_modes.dust`)"), "{message}");
+        assert!(message.contains("glow"), "{message}");
+    }
+
+    #[test]
+    fn bad_reload_keeps_options() {
+        let path = write("bad-reload", "stars = false\n");
+        let mut world = World::new();
+        world.insert_resource(Options::default());
+        let reload = |world: &mut World, path: PathBuf| {
+            world.run_system_once(move |mut options: ResMut<Options>| {
+                refresh_options_from_file(&path, &mut options)
+            })
+        };
+
+        assert!(reload(&mut world, path.clone()));
+        let loaded = world.resource::<Options>().clone();
+        assert!(!loaded.stars);
+
+        fs::write(&path, "stars = \"no\"\n").unwrap();
+        assert!(!reload(&mut world, path.clone()));
+        assert!(*world.resource::<Options>() == loaded);
+        fs::remove_file(&path).unwrap();
+    }
 }
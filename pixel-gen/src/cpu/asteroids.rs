@@ -0,0 +1,86 @@
+//! Port of `assets/shaders/asteroids.glsl`.
+
+use super::{
+    glsl::{dither, fract, interpolate, step},
+    Fragment, Texture,
+};
+use crate::{asteroids::AsteroidMaterial, *};
+
+pub struct Asteroids<'a> {
+    material: &'a AsteroidMaterial,
+    colors: Texture<'a>,
+}
+
+impl<'a> Asteroids<'a> {
+    pub fn new(material: &'a AsteroidMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Asteroids {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+        })
+    }
+}
+
+fn rand(coord: Vec2) -> f32 {
+    // 43758.5453 as the shader compiler rounds it to f32
+    fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * 43758.547)
+}
+
+fn noise(coord: Vec2) -> f32 {
+    let i = coord.floor();
+    let f = coord - i;
+
+    let a = rand(i);
+    let b = rand(i + Vec2::new(1.0, 0.0));
+    let c = rand(i + Vec2::new(0.0, 1.0));
+    let d = rand(i + Vec2::new(1.0, 1.0));
+
+    interpolate(f, a, b, c, d)
+}
+
+fn fbm(mut coord: Vec2) -> f32 {
+    let mut value = 0.0;
+    let mut scale = 0.5;
+
+    for _ in 0..3 {
+        value += noise(coord) * scale;
+        coord *= 2.0;
+        scale *= 0.5;
+    }
+    value
+}
+
+impl Fragment for Asteroids<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let material = self.material;
+
+        // pixelizing and dithering
+        let uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // a lumpy outline, from noise sampled around a circle so it wraps
+        let p = (uv - 0.5) * 2.0;
+        let d = p.length();
+        let angle = p.y.atan2(p.x);
+        let edge = 0.6 + fbm(Vec2::new(angle.cos(), angle.sin()) * 1.5 + material.seed) * 0.45;
+
+        // shading from the light, darkest colors left for craters and the rim
+        let mut light = (1.0 - uv.distance(material.light_origin) * 1.2).clamp(0.0, 1.0);
+        if dith {
+            light *= 0.9;
+        }
+        let mut k = 1.0 + (light * 3.99).floor();
+
+        if fbm(uv * 4.0 + material.seed * 2.0) < 0.3 {
+            k -= 1.0;
+        }
+        if d > edge - 0.2 {
+            k -= 1.0;
+        }
+
+        let col = self
+            .colors
+            .sample(Vec2::new(k.max(1.0) / 7.0, 0.0))
+            .truncate();
+        col.extend(step(d, edge))
+    }
+}
@@ -0,0 +1,49 @@
+//! Port of `assets/shaders/atmosphere.glsl`.
+
+use super::{
+    glsl::{dither, step},
+    Fragment, Texture,
+};
+use crate::{atmosphere::AtmosphereMaterial, *};
+
+pub struct Atmosphere<'a> {
+    material: &'a AtmosphereMaterial,
+    colors: Texture<'a>,
+}
+
+impl<'a> Atmosphere<'a> {
+    pub fn new(material: &'a AtmosphereMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Atmosphere {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+        })
+    }
+}
+
+impl Fragment for Atmosphere<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let material = self.material;
+
+        // pixelizing and dithering
+        let uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // 0 at the planet's edge, 1 where the glow ends
+        let d = uv.distance(Vec2::splat(0.5)) * 2.0;
+        let mut t = (d - material.inner) / (1.0 - material.inner);
+        if dith {
+            t *= 1.1;
+        }
+
+        // brighter on the side the planet is lit from
+        let light = (1.0 - uv.distance(material.light_origin) * 1.5).clamp(0.0, 1.0);
+
+        // fade out in a few hard steps
+        let glow = ((1.0 - t).clamp(0.0, 1.0) * 3.0).floor() / 3.0;
+        let a = glow * (0.1 + light * 0.7) * step(material.inner, d) * step(d, 1.0);
+
+        let k = 5.0 + (light * 1.99).floor();
+        let col = self.colors.sample(Vec2::new(k / 7.0, 0.0)).truncate();
+        col.extend(a)
+    }
+}
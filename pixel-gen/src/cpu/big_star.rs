@@ -0,0 +1,55 @@
+//! Port of `assets/shaders/big_star.glsl`.
+
+use super::{Fragment, Texture};
+use crate::{stars::BigStarMaterial, *};
+
+const SHEET_SIZE: Vec2 = Vec2::new(144., 24.);
+const SPRITE_SIZE: Vec2 = Vec2::new(24., 24.);
+
+pub struct BigStar<'a> {
+    material: &'a BigStarMaterial,
+    colors: Texture<'a>,
+    star: Texture<'a>,
+}
+
+impl<'a> BigStar<'a> {
+    pub fn new(material: &'a BigStarMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(BigStar {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+            star: Texture::new(images, &material.image)?,
+        })
+    }
+
+    fn sprite_sheet_uv(&self, uv: Vec2) -> Vec2 {
+        // Calculate the number of sprites per row and column
+        let sprite_count = SHEET_SIZE / SPRITE_SIZE;
+
+        // Calculate the row and column of the selected sprite
+        let sprite_row = self.material.star_type / sprite_count.x as i32;
+        let sprite_col = self.material.star_type % sprite_count.x as i32;
+
+        // Base UV coordinates for the selected sprite
+        let sprite_uv_offset =
+            Vec2::new(sprite_col as f32, sprite_row as f32) * SPRITE_SIZE / SHEET_SIZE;
+
+        // Scale the input UV to fit the size of one sprite
+        let scaled_uv = uv * SPRITE_SIZE / SHEET_SIZE;
+
+        sprite_uv_offset + scaled_uv
+    }
+}
+
+impl Fragment for BigStar<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let uv = self.sprite_sheet_uv(vertex_uv);
+        let star = self.star.sample(uv);
+        let col_val = (star.x * self.material.brightness).clamp(0., 1.);
+        let a = star.w;
+        let replace_col = self
+            .colors
+            .sample(Vec2::new((col_val * 7.0).round() / 7.0, 0.0));
+
+        replace_col.truncate().extend(a)
+    }
+}
@@ -0,0 +1,129 @@
+//! Port of `assets/shaders/black_hole.glsl`.
+
+use super::{
+    glsl::{dither, fract, interpolate, mix},
+    Fragment, Texture,
+};
+use crate::{black_hole::BlackHoleMaterial, *};
+
+pub struct BlackHole<'a> {
+    material: &'a BlackHoleMaterial,
+    colors: Texture<'a>,
+}
+
+impl<'a> BlackHole<'a> {
+    pub fn new(material: &'a BlackHoleMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(BlackHole {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+        })
+    }
+
+    // gas streaking round at radius r, the inner orbits faster: 1 / r turns
+    // for each turn of time, blended between whole numbers of turns so every
+    // orbit comes back round as time wraps at TAU
+    fn swirl(&self, angle: f32, r: f32) -> f32 {
+        let speed = 1.0 / r.max(0.1);
+        let slow = speed.floor();
+        let orbit = Vec2::new(r * 8.0, self.material.seed);
+        let at = |phase: f32| fbm(Vec2::new(phase.cos(), phase.sin()) * 3.0 + orbit);
+        let time = self.material.time;
+        mix(
+            at(angle + time * slow),
+            at(angle + time * (slow + 1.0)),
+            speed - slow,
+        )
+    }
+
+    fn color(&self, density: f32) -> Vec4 {
+        let k = (1.0 + (density * 6.0).floor()).clamp(1.0, 7.0);
+        self.colors
+            .sample(Vec2::new(k / 7.0, 0.0))
+            .truncate()
+            .extend(1.0)
+    }
+}
+
+fn rand(coord: Vec2) -> f32 {
+    // 43758.5453 as the shader compiler rounds it to f32
+    fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * 43758.547)
+}
+
+fn noise(coord: Vec2) -> f32 {
+    let i = coord.floor();
+    let f = coord - i;
+
+    let a = rand(i);
+    let b = rand(i + Vec2::new(1.0, 0.0));
+    let c = rand(i + Vec2::new(0.0, 1.0));
+    let d = rand(i + Vec2::new(1.0, 1.0));
+
+    interpolate(f, a, b, c, d)
+}
+
+fn fbm(mut coord: Vec2) -> f32 {
+    let mut value = 0.0;
+    let mut scale = 0.5;
+
+    for _ in 0..3 {
+        value += noise(coord) * scale;
+        coord *= 2.0;
+        scale *= 0.5;
+    }
+    value
+}
+
+impl Fragment for BlackHole<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let material = self.material;
+        let horizon = material.horizon;
+
+        // pixelizing and dithering
+        let uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // y up, and the rotation undone, so the disk lies along x with its near
+        // side below
+        let p = (uv - 0.5) * Vec2::new(2.0, -2.0);
+        let (sin, cos) = material.rotation.sin_cos();
+        let p = Vec2::new(cos * p.x + sin * p.y, -sin * p.x + cos * p.y);
+        let r = p.length();
+
+        // the disk in its own plane, where it is a circle of radius 1
+        let q = Vec2::new(p.x, p.y / material.tilt);
+        let r_disk = q.length();
+        let n = self.swirl(q.y.atan2(q.x), r_disk);
+        // hotter towards the middle, with a ragged outer edge
+        let mut density = (1.0 - (r_disk - horizon) / (1.0 - horizon)) * 0.7 + n * 0.5;
+        if dith {
+            density *= 0.9;
+        }
+        let in_disk = r_disk > horizon * 1.3 && r_disk < 0.7 + n * 0.4;
+
+        // light from the far side of the disk bent up over the top of the hole,
+        // and a thin ring of it right at the edge of the horizon
+        let arc = horizon * (1.15 + 0.5 * (p.y / r.max(0.001)).max(0.0));
+        let mut lensed = self.swirl(p.y.atan2(p.x), r / horizon * 0.3) * 0.6 + 0.4;
+        if dith {
+            lensed *= 0.9;
+        }
+
+        let mut col = Vec4::ZERO;
+        if in_disk && q.y > 0.0 {
+            col = self.color(density);
+        }
+        if r > horizon && r < horizon * 1.12 {
+            col = self.color(1.0);
+        } else if r > horizon && r < arc {
+            col = self.color(lensed);
+        }
+        if r <= horizon {
+            col = self.colors.sample(Vec2::ZERO).truncate().extend(1.0);
+        }
+        if in_disk && q.y <= 0.0 {
+            col = self.color(density);
+        }
+
+        col
+    }
+}
@@ -0,0 +1,92 @@
+//! Port of `assets/shaders/galaxies.glsl`.
+
+use super::{
+    glsl::{dither, fract, interpolate, step},
+    Fragment, Texture,
+};
+use crate::{galaxies::GalaxyMaterial, *};
+
+pub struct Galaxies<'a> {
+    material: &'a GalaxyMaterial,
+    colors: Texture<'a>,
+}
+
+impl<'a> Galaxies<'a> {
+    pub fn new(material: &'a GalaxyMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Galaxies {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+        })
+    }
+}
+
+fn rand(coord: Vec2) -> f32 {
+    // 43758.5453 as the shader compiler rounds it to f32
+    fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * 43758.547)
+}
+
+fn noise(coord: Vec2) -> f32 {
+    let i = coord.floor();
+    let f = coord - i;
+
+    let a = rand(i);
+    let b = rand(i + Vec2::new(1.0, 0.0));
+    let c = rand(i + Vec2::new(0.0, 1.0));
+    let d = rand(i + Vec2::new(1.0, 1.0));
+
+    interpolate(f, a, b, c, d)
+}
+
+fn fbm(mut coord: Vec2) -> f32 {
+    let mut value = 0.0;
+    let mut scale = 0.5;
+
+    for _ in 0..3 {
+        value += noise(coord) * scale;
+        coord *= 2.0;
+        scale *= 0.5;
+    }
+    value
+}
+
+impl Fragment for Galaxies<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let material = self.material;
+
+        // pixelizing and dithering
+        let uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // undo the rotation and the tilt, so the disk is a circle of radius 1
+        let p = (uv - 0.5) * 2.0;
+        let (sin, cos) = material.rotation.sin_cos();
+        let mut p = Vec2::new(cos * p.x + sin * p.y, -sin * p.x + cos * p.y);
+        p.y /= material.tilt;
+        let r = p.length();
+
+        // clumps of stars and dust
+        let n = fbm(p * 3.0 + material.seed);
+        // bright core
+        let core = (1.0 - r / material.bulge).clamp(0.0, 1.0);
+
+        let mut density = if material.elliptical == 1 {
+            let falloff = (1.0 - r).clamp(0.0, 1.0);
+            falloff * falloff * (0.8 + n * 0.4) + core * 0.5
+        } else {
+            // arms winding further round the further out they are
+            let angle = p.y.atan2(p.x);
+            let arm =
+                (0.5 + 0.5 * (material.arms as f32 * (angle - material.twist * r)).cos()).powf(4.0);
+            let disk = arm * (1.0 - r).clamp(0.0, 1.0) * (0.4 + n * 1.2);
+            disk + core
+        };
+
+        if dith {
+            density *= 0.9;
+        }
+
+        let k = (1.0 + (density * 5.0).floor()).min(7.0);
+        let col = self.colors.sample(Vec2::new(k / 7.0, 0.0)).truncate();
+        col.extend(step(0.1, density))
+    }
+}
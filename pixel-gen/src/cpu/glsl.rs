@@ -0,0 +1,48 @@
+//! The handful of GLSL builtins the layer shaders use, with GLSL semantics.
+
+use bevy::math::Vec2;
+
+pub fn fract(x: f32) -> f32 {
+    x - x.floor()
+}
+
+pub fn fract2(v: Vec2) -> Vec2 {
+    v - v.floor()
+}
+
+/// GLSL `mod`, which takes the sign of `y` unlike `%`.
+pub fn modulo(x: f32, y: f32) -> f32 {
+    x - y * (x / y).floor()
+}
+
+pub fn modulo2(v: Vec2, m: Vec2) -> Vec2 {
+    Vec2::new(modulo(v.x, m.x), modulo(v.y, m.y))
+}
+
+pub fn step(edge: f32, x: f32) -> f32 {
+    if x < edge {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+pub fn mix(x: f32, y: f32, a: f32) -> f32 {
+    x * (1.0 - a) + y * a
+}
+
+/// `bool dither(vec2 uv1, vec2 uv2)`, shared by every noise shader.
+pub fn dither(uv1: Vec2, uv2: Vec2, pixels: f32) -> bool {
+    modulo(uv1.y + uv2.x, 2.0 / pixels) <= 1.0 / pixels
+}
+
+/// The value-noise interpolation `noise()` does after its four `rand()` lookups.
+pub fn interpolate(f: Vec2, a: f32, b: f32, c: f32, d: f32) -> f32 {
+    let cubic = f * f * (3.0 - 2.0 * f);
+    mix(a, b, cubic.x) + (c - a) * cubic.y * (1.0 - cubic.x) + (d - b) * cubic.x * cubic.y
+}
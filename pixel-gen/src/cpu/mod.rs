@@ -0,0 +1,382 @@
+//! Software ports of the layer shaders in `assets/shaders`.
+//!
+//! Each layer is a [`Fragment`] built from the same material the GPU path
+//! spawns, and mirrors its `.glsl` file function for function. Layers are
+//! rasterised onto a [`Frame`] with the same quad placement and blending the
+//! 2d pipeline uses, so a frame matches what the camera renders (MSAA on mesh
+//! edges aside).
+
+mod asteroids;
+mod atmosphere;
+mod big_star;
+mod black_hole;
+mod galaxies;
+mod glsl;
+mod nebulae;
+mod planets;
+mod rings;
+mod star_stuff;
+
+use bevy::{
+    color::{ColorToComponents, ColorToPacked, LinearRgba, Srgba},
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use crate::*;
+
+pub use asteroids::Asteroids;
+pub use atmosphere::Atmosphere;
+pub use big_star::BigStar;
+pub use black_hole::BlackHole;
+pub use galaxies::Galaxies;
+pub use nebulae::Nebulae;
+pub use planets::Planets;
+pub use rings::Rings;
+pub use star_stuff::StarStuff;
+
+/// A fragment shader: maps a mesh UV to the colour it writes.
+pub trait Fragment {
+    fn fragment(&mut self, uv: Vec2) -> Vec4;
+}
+
+impl<F: FnMut(Vec2) -> Vec4> Fragment for F {
+    fn fragment(&mut self, uv: Vec2) -> Vec4 {
+        self(uv)
+    }
+}
+
+/// Linear, straight-alpha RGBA pixels, as the render target stores them.
+#[derive(Clone)]
+pub struct Frame {
+    pub size: UVec2,
+    pub pixels: Vec<Vec4>,
+}
+
+impl Frame {
+    pub fn new(size: UVec2) -> Self {
+        Frame {
+            size,
+            pixels: vec![Vec4::ZERO; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Renders `shader` over the whole frame, like a full screen quad.
+    pub fn render(size: UVec2, shader: &mut impl Fragment) -> Self {
+        let mut frame = Frame::new(size);
+        frame.draw(frame.world_rect(), shader);
+        frame
+    }
+
+    /// World space covered by the frame; the camera sits at the origin.
+    pub fn world_rect(&self) -> Rect {
+        Rect::from_center_size(Vec2::ZERO, self.size.as_vec2())
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Vec4 {
+        self.pixels[(y * self.size.x + x) as usize]
+    }
+
+    /// The frame inside a border `rim` pixels wide.
+    pub fn without_rim(&self, rim: u32) -> Frame {
+        let size = self.size.saturating_sub(UVec2::splat(2 * rim));
+        let pixels = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| self.get(x + rim, y + rim)))
+            .collect();
+        Frame { size, pixels }
+    }
+
+    /// Blends `shader` into every pixel whose centre lies in `quad`, given in
+    /// world space (y up), the way a `Rectangle` mesh is rasterised.
+    pub fn draw(&mut self, quad: Rect, shader: &mut impl Fragment) {
+        self.draw_blended(quad, shader, BlendMode::Alpha);
+    }
+
+    /// Like [`Frame::draw`], compositing with `mode` instead of alpha blending.
+    pub fn draw_blended(&mut self, quad: Rect, shader: &mut impl Fragment, mode: BlendMode) {
+        self.draw_bleeding(quad, 0., shader, mode);
+    }
+
+    /// Like [`Frame::draw_blended`], but also covers the pixels up to `bleed`
+    /// past each edge of `quad`, with UVs running on past 0 and 1: what the
+    /// quad would draw if it were that much bigger without stretching.
+    pub fn draw_bleeding(
+        &mut self,
+        quad: Rect,
+        bleed: f32,
+        shader: &mut impl Fragment,
+        mode: BlendMode,
+    ) {
+        let area = quad.inflate(bleed);
+        let half = self.size.as_vec2() / 2.;
+        let first = (area.min.x + half.x - 0.5).ceil().max(0.) as u32;
+        let last = (area.max.x + half.x - 0.5).ceil().min(self.size.x as f32) as u32;
+        let top = (half.y - area.max.y - 0.5).ceil().max(0.) as u32;
+        let bottom = (half.y - area.min.y - 0.5).ceil().min(self.size.y as f32) as u32;
+
+        for y in top..bottom {
+            for x in first..last {
+                let centre = Vec2::new(x as f32 + 0.5 - half.x, half.y - (y as f32 + 0.5));
+                let uv = Vec2::new(
+                    (centre.x - quad.min.x) / quad.width(),
+                    (quad.max.y - centre.y) / quad.height(),
+                );
+                let index = (y * self.size.x + x) as usize;
+                self.pixels[index] = blend(shader.fragment(uv), self.pixels[index], mode);
+            }
+        }
+    }
+
+    /// The frame's pixels as sRGB RGBA bytes, as they would be presented.
+    pub fn to_srgba8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|pixel| {
+                let srgb = Srgba::from(LinearRgba::from_vec4(pixel.clamp(Vec4::ZERO, Vec4::ONE)));
+                srgb.to_u8_array()
+            })
+            .collect()
+    }
+
+    /// Encodes the frame into an sRGB image, as it would be presented.
+    pub fn to_image(&self) -> Image {
+        let data = self.to_srgba8();
+
+        Image::new(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+/// The blend state `mode` gives a `Material2d` pipeline, see
+/// `BlendMode::specialize`.
+///
+/// Over an opaque pixel this is exactly what the GPU does. Over a transparent
+/// one the colour is un-premultiplied again, so a frame keeps straight alpha
+/// and exports composite correctly elsewhere.
+fn blend(src: Vec4, dst: Vec4, mode: BlendMode) -> Vec4 {
+    if src.w <= 0. {
+        return dst;
+    }
+    let alpha = src.w + dst.w * (1. - src.w);
+    let (s, d) = (src.truncate() * src.w, dst.truncate() * dst.w);
+    let rgb = match mode {
+        BlendMode::Alpha => s + d * (1. - src.w),
+        BlendMode::Additive => s + d,
+        BlendMode::Screen => s + d * (Vec3::ONE - s),
+        BlendMode::Multiply => s * d + d * (1. - src.w),
+    };
+    (rgb / alpha).extend(alpha)
+}
+
+/// An `Image` sampled the way the default `ImagePlugin` sampler does:
+/// bilinear, clamped to the edge, sRGB decoded for sRGB formats.
+pub struct Texture<'a> {
+    image: &'a Image,
+    srgb: bool,
+}
+
+impl<'a> Texture<'a> {
+    pub fn new(images: &'a Assets<Image>, handle: &Option<Handle<Image>>) -> Option<Self> {
+        let image = images.get(handle.as_ref()?)?;
+        let srgb = match image.texture_descriptor.format {
+            TextureFormat::Rgba8UnormSrgb => true,
+            TextureFormat::Rgba8Unorm => false,
+            _ => return None,
+        };
+        Some(Texture { image, srgb })
+    }
+
+    fn texel(&self, x: i32, y: i32) -> Vec4 {
+        let size = self.image.size().as_ivec2();
+        let x = x.clamp(0, size.x - 1);
+        let y = y.clamp(0, size.y - 1);
+        let i = ((y * size.x + x) * 4) as usize;
+        let [r, g, b, a] = [0, 1, 2, 3].map(|c| self.image.data[i + c] as f32 / 255.);
+
+        if self.srgb {
+            Vec4::new(
+                Srgba::gamma_function(r),
+                Srgba::gamma_function(g),
+                Srgba::gamma_function(b),
+                a,
+            )
+        } else {
+            Vec4::new(r, g, b, a)
+        }
+    }
+
+    pub fn sample(&self, uv: Vec2) -> Vec4 {
+        let texel = uv * self.image.size().as_vec2() - 0.5;
+        let base = texel.floor();
+        let t = texel - base;
+        let (x, y) = (base.x as i32, base.y as i32);
+
+        let top = self.texel(x, y).lerp(self.texel(x + 1, y), t.x);
+        let bottom = self.texel(x, y + 1).lerp(self.texel(x + 1, y + 1), t.x);
+        top.lerp(bottom, t.y)
+    }
+}
+
+/// Memoises work that only depends on the pixelated uv, `floor(UV * pixels)`.
+struct PixelCells<T> {
+    pixels: f32,
+    columns: usize,
+    values: Vec<Option<T>>,
+}
+
+impl<T: Copy> PixelCells<T> {
+    const MAX_CELLS: usize = 1 << 22;
+
+    fn new(pixels: f32) -> Self {
+        let columns = pixels.max(1.).ceil() as usize;
+        let cells = columns.saturating_mul(columns);
+        PixelCells {
+            pixels,
+            columns,
+            values: if cells <= Self::MAX_CELLS {
+                vec![None; cells]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    fn get_or_insert_with(&mut self, uv: Vec2, f: impl FnOnce() -> T) -> T {
+        let cell = (uv * self.pixels).floor();
+        let in_range = |v: f32| v >= 0. && (v as usize) < self.columns;
+        if self.values.is_empty() || !in_range(cell.x) || !in_range(cell.y) {
+            return f();
+        }
+
+        let index = cell.y as usize * self.columns + cell.x as usize;
+        *self.values[index].get_or_insert_with(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Options, headless};
+
+    const SIZE: Vec2 = Vec2::new(160., 90.);
+
+    fn options(seed: u32) -> Options {
+        let mut options = Options {
+            seed: GenerationSeed(seed),
+            black_hole: true,
+            ..Options::default()
+        };
+        options.screen_size = ScreenSize {
+            screen_space: utils::screenspace::Space {
+                width: SIZE.x,
+                height: SIZE.y,
+            },
+            show_ui: false,
+        };
+        options
+    }
+
+    /// Options drawing nothing but what `enable` turns back on, over no
+    /// background.
+    fn only(enable: fn(&mut Options)) -> Options {
+        let mut options = Options {
+            transparency: true,
+            nebulae: false,
+            dust: false,
+            stars: false,
+            galaxies: false,
+            asteroids: false,
+            black_hole: false,
+            planets: false,
+            ..options(7)
+        };
+        enable(&mut options);
+        options
+    }
+
+    fn render(options: &Options) -> Frame {
+        headless::render(options, &headless::assets_dir()).unwrap()
+    }
+
+    /// How many pixels the layers drew, and the index and sRGB bytes of the
+    /// first.
+    type Drawn = (usize, Option<(usize, [u8; 4])>);
+
+    /// A layer's name, how to turn it on and what it draws.
+    type Golden = (&'static str, fn(&mut Options), Drawn);
+
+    fn drawn(frame: &Frame) -> Drawn {
+        let image = frame.to_image();
+        let mut pixels = image
+            .data
+            .chunks_exact(4)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[3] > 0);
+        let first = pixels
+            .next()
+            .map(|(i, pixel)| (i, [pixel[0], pixel[1], pixel[2], pixel[3]]));
+        (first.is_some() as usize + pixels.count(), first)
+    }
+
+    #[test]
+    fn same_seed_draws_the_same_frame() {
+        let (first, second) = (render(&options(7)), render(&options(7)));
+        assert_eq!(first.pixels, second.pixels);
+        assert_ne!(first.pixels, render(&options(8)).pixels);
+    }
+
+    #[test]
+    fn layers_draw_their_golden_pixels() {
+        let layers: [Golden; 7] = [
+            (
+                "nebulae",
+                |o| o.nebulae = true,
+                (2396, Some((0, [169, 31, 101, 255]))),
+            ),
+            (
+                "dust",
+                |o| o.dust = true,
+                (5280, Some((0, [43, 15, 84, 64]))),
+            ),
+            (
+                "stars",
+                |o| o.stars = true,
+                (4899, Some((85, [43, 15, 84, 26]))),
+            ),
+            (
+                "galaxies",
+                |o| o.galaxies = true,
+                (4704, Some((69, [169, 31, 101, 255]))),
+            ),
+            (
+                "asteroids",
+                |o| o.asteroids = true,
+                (738, Some((86, [169, 31, 101, 255]))),
+            ),
+            (
+                "black hole",
+                |o| o.black_hole = true,
+                (3580, Some((24, [52, 112, 221, 255]))),
+            ),
+            (
+                "planets",
+                |o| o.planets = true,
+                (9149, Some((18, [255, 79, 105, 255]))),
+            ),
+        ];
+        for (layer, enable, golden) in layers {
+            assert_eq!(drawn(&render(&only(enable))), golden, "{layer}");
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Port of `assets/shaders/nebulae.glsl`.
+
+use std::f32::consts::TAU;
+
+use super::{
+    glsl::{dither, fract, fract2, interpolate, modulo2, smoothstep, step},
+    Fragment, PixelCells, Texture,
+};
+use crate::{nebulae::NebulaeMaterial, *};
+
+pub struct Nebulae<'a> {
+    shader: Shader<'a>,
+    colors: Texture<'a>,
+    cells: PixelCells<(f32, f32)>,
+}
+
+/// The shader's helper functions, which only need the uniforms.
+struct Shader<'a> {
+    material: &'a NebulaeMaterial,
+}
+
+impl<'a> Nebulae<'a> {
+    pub fn new(material: &'a NebulaeMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Nebulae {
+            shader: Shader { material },
+            colors: Texture::new(images, &material.color_texture)?,
+            cells: PixelCells::new(material.pixels),
+        })
+    }
+}
+
+impl Shader<'_> {
+    fn tiles(&self) -> bool {
+        self.material.should_tile == 1
+    }
+
+    /// Noise cells across the quad at `scale`, whole ones when tiling.
+    fn cells(&self, scale: f32) -> Vec2 {
+        if self.tiles() {
+            return (scale * self.material.uv_correct).round().max(Vec2::ONE);
+        }
+        scale * self.material.uv_correct
+    }
+
+    fn rand(&self, mut coord: Vec2, tilesize: f32) -> f32 {
+        if self.tiles() {
+            coord = modulo2(coord, self.cells(tilesize));
+        }
+
+        fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * (15.5453 + self.material.seed))
+    }
+
+    fn noise(&self, coord: Vec2, tilesize: f32) -> f32 {
+        let i = coord.floor();
+        let f = fract2(coord);
+
+        let a = self.rand(i, tilesize);
+        let b = self.rand(i + Vec2::new(1.0, 0.0), tilesize);
+        let c = self.rand(i + Vec2::new(0.0, 1.0), tilesize);
+        let d = self.rand(i + Vec2::new(1.0, 1.0), tilesize);
+
+        interpolate(f, a, b, c, d)
+    }
+
+    fn fbm(&self, mut coord: Vec2, tilesize: f32) -> f32 {
+        let mut value = 0.0;
+        let mut scale = 0.5;
+
+        for _ in 0..self.material.octaves {
+            value += self.noise(coord, tilesize) * scale;
+            coord *= 2.0;
+            scale *= 0.5;
+        }
+        value
+    }
+
+    fn circle_noise(&self, mut uv: Vec2, tilesize: f32) -> f32 {
+        if self.tiles() {
+            uv = modulo2(uv, self.cells(tilesize));
+        }
+
+        let uv_y = uv.y.floor();
+        uv.x += uv_y * 0.32;
+        let f = fract2(uv);
+        let h = self.rand(Vec2::new(uv.x.floor(), uv_y.floor()), tilesize);
+        let m = (f - 0.25 - (h * 0.5)).length();
+        let r = h * 0.25;
+        smoothstep(0.0, r, m * 0.75)
+    }
+
+    fn cloud_alpha(&self, uv: Vec2, tilesize: f32) -> f32 {
+        let mut c_noise = 0.0;
+
+        let iters = 2;
+        for i in 0..iters {
+            c_noise += self.circle_noise(
+                uv * self.cells(tilesize * 0.5) + ((i + 1) as f32) + Vec2::new(-0.3, 0.0),
+                (tilesize * 0.5).ceil(),
+            );
+        }
+        self.fbm(uv * self.cells(tilesize) + c_noise, tilesize)
+    }
+
+    /// How far the flow pushes the clouds at `uv`, looping with `time`.
+    fn flow_warp(&self, uv: Vec2, tilesize: f32) -> Vec2 {
+        let material = self.material;
+        if material.flow <= 0. {
+            return Vec2::ZERO;
+        }
+
+        let angle = material.time * TAU;
+        let coord = uv * self.cells(tilesize) + Vec2::from_angle(angle);
+        let push = Vec2::new(
+            self.fbm(coord + Vec2::new(1.7, 9.2), tilesize),
+            self.fbm(coord + Vec2::new(8.3, 2.8), tilesize),
+        );
+        (push - 0.5) * material.flow / self.cells(tilesize)
+    }
+}
+
+impl Fragment for Nebulae<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let shader = &self.shader;
+        let material = shader.material;
+        // scrolled by parallax mode, which always tiles
+        let vertex_uv = vertex_uv + material.scroll;
+        let mut cell = (vertex_uv * material.pixels).floor();
+        // a tile over samples the very same cells, not ones a rounding error away
+        if shader.tiles() {
+            cell = modulo2(cell, Vec2::splat(material.pixels));
+        }
+        let uv = cell / material.pixels;
+
+        // distance from center
+        let mut d = uv.distance(Vec2::splat(0.5)) * 0.4;
+
+        // the uncorrected grid repeats every tile
+        let grid = if shader.tiles() {
+            uv
+        } else {
+            uv * material.uv_correct
+        };
+        let dith = dither(grid, vertex_uv, material.pixels);
+
+        // noise for the inside of the nebulae
+        let size = material.size;
+        let (n, n2) = self.cells.get_or_insert_with(vertex_uv, || {
+            let uv = uv + shader.flow_warp(uv, size);
+            (
+                shader.cloud_alpha(uv, size),
+                shader.fbm(uv * shader.cells(size) + Vec2::new(1., 1.), size),
+            )
+        });
+        let n_lerp = n2 * n;
+        // `cloud_alpha` is deterministic, so the shader's second call equals `n`
+        let n_dust = n;
+        let mut n_dust_lerp = n_dust * n_lerp;
+
+        // apply dithering (the shader also scales `n_lerp`, which is never read again)
+        if dith {
+            n_dust_lerp *= 0.95;
+            d *= 0.98;
+        }
+
+        // slightly offset alpha values to create thin bands around the nebulae
+        let mut a = step(n2, 0.1 + d);
+        let mut a2 = step(n2, 0.115 + d);
+        if shader.tiles() {
+            a = step(n2, 0.3);
+            a2 = step(n2, 0.315);
+        }
+
+        // choose colors
+        if material.reduce_background == 1 {
+            n_dust_lerp = n_dust_lerp.powf(1.2) * 0.7;
+        }
+        let col_value = if a2 > a {
+            (n_dust_lerp * 35.0).floor() / 7.0
+        } else {
+            (n_dust_lerp * 14.0).floor() / 7.0
+        };
+
+        // apply colors
+        let mut col = self.colors.sample(Vec2::new(col_value, 0.0)).truncate();
+        if col_value < 0.1 {
+            col = material.background_color.truncate();
+            // with nothing behind the nebulae, fade its darkest band out in steps
+            // instead of painting the background over whatever is underneath
+            if material.transparent == 1 {
+                a2 *= ((n_dust_lerp * 56.0).floor() / 4.0).min(1.0);
+            }
+        }
+
+        col.extend(a2)
+    }
+}
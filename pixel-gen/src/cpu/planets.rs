@@ -0,0 +1,217 @@
+//! Port of `assets/shaders/planets.glsl`.
+
+use super::{
+    glsl::{dither, fract, fract2, interpolate, modulo, step},
+    Fragment, PixelCells, Texture,
+};
+use crate::{
+    planets::{PlanetKind, PlanetsMaterial, TURN},
+    *,
+};
+
+pub struct Planets<'a> {
+    shader: Shader<'a>,
+    colors: Texture<'a>,
+    cells: PixelCells<Vec2>,
+}
+
+/// The shader's helper functions, which only need the uniforms.
+struct Shader<'a> {
+    material: &'a PlanetsMaterial,
+}
+
+impl<'a> Planets<'a> {
+    pub fn new(material: &'a PlanetsMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Planets {
+            shader: Shader { material },
+            colors: Texture::new(images, &material.color_texture)?,
+            cells: PixelCells::new(material.pixels),
+        })
+    }
+}
+
+impl Shader<'_> {
+    /// Wraps every `period` along x, so the surface meets itself round the back.
+    fn rand(&self, mut coord: Vec2, period: f32) -> f32 {
+        coord.x = modulo(coord.x, period);
+        fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * 15.5453 * self.material.seed)
+    }
+
+    fn noise(&self, coord: Vec2, period: f32) -> f32 {
+        let i = coord.floor();
+        let f = fract2(coord);
+
+        let a = self.rand(i, period);
+        let b = self.rand(i + Vec2::new(1.0, 0.0), period);
+        let c = self.rand(i + Vec2::new(0.0, 1.0), period);
+        let d = self.rand(i + Vec2::new(1.0, 1.0), period);
+
+        interpolate(f, a, b, c, d)
+    }
+
+    fn fbm(&self, mut coord: Vec2, period: f32) -> f32 {
+        let mut value = 0.0;
+        let mut scale = 0.5;
+
+        for _ in 0..self.material.octaves {
+            value += self.noise(coord, period) * scale;
+            coord *= 2.0;
+            scale *= 0.5;
+        }
+        value
+    }
+
+    fn kind(&self) -> PlanetKind {
+        PlanetKind::ALL
+            .get(self.material.kind as usize)
+            .copied()
+            .unwrap_or(PlanetKind::Dry)
+    }
+
+    /// The noise a planet's surface is built from; what the two values mean
+    /// depends on the kind.
+    fn surface(&self, uv: Vec2) -> Vec2 {
+        let size = self.material.size;
+        // a whole number of noise cells round the planet at every scale below
+        let scale = Vec2::new(size.round().max(1.0), size);
+        let period = scale.x * TURN;
+        let n = self.fbm(uv * scale, period);
+        match self.kind() {
+            // land height, polar cap edge
+            PlanetKind::Terran => Vec2::new(self.fbm(uv * scale + n * 2.0, period), n),
+            // frost, cracks
+            PlanetKind::Ice => Vec2::new(n, self.fbm(uv * scale * 1.5 + 7.0, period * 1.5)),
+            // crust, rivers
+            PlanetKind::Lava => {
+                Vec2::new(n, self.fbm(uv * scale * 1.5 + n * 2.0 + 3.0, period * 1.5))
+            }
+            // turbulence, stretched so it runs along the bands
+            PlanetKind::GasGiant => Vec2::new(
+                self.fbm(uv * scale * Vec2::new(0.5, 2.0), period * 0.5),
+                0.0,
+            ),
+            PlanetKind::Dry => Vec2::new(n, self.fbm(uv * scale + n * 3.0, period)),
+        }
+    }
+
+    /// Where on the surface a point of the spherified face is, once tilted and
+    /// turned.
+    fn turn(&self, uv: Vec2) -> Vec2 {
+        let mut uv = rotate(uv, self.material.tilt);
+        uv.x -= self.material.time * TURN;
+        uv
+    }
+
+    /// Position in the gradient, darkest color at 0 and brightest at 7 / 7.
+    fn col_value(&self, n: Vec2, uv: Vec2, d_to_light: f32, dith: bool) -> f32 {
+        let light = light_level(d_to_light, dith);
+
+        let k = match self.kind() {
+            PlanetKind::Terran => {
+                if (uv.y - 0.5).abs() > 0.38 - n.y * 0.15 {
+                    5.0 + light
+                } else if n.x < 0.45 {
+                    1.0 + (light * 0.5).floor()
+                } else {
+                    3.0 + light
+                }
+            }
+            PlanetKind::Ice => {
+                let mut k = 4.0 + light;
+                if n.x < 0.35 {
+                    k -= 1.0;
+                }
+                if (n.y - 0.45).abs() < 0.015 {
+                    k = 2.0 + (light * 0.5).floor();
+                }
+                k
+            }
+            PlanetKind::Lava => {
+                // rivers glow, so they ignore the light
+                let river = (n.y - 0.45).abs();
+                if river < 0.012 {
+                    7.0
+                } else if river < 0.024 {
+                    5.0
+                } else {
+                    (light * 0.67).floor() + step(0.5, n.x)
+                }
+            }
+            PlanetKind::GasGiant => {
+                let bands = uv.y * self.material.size * 0.75 + n.x * 1.5;
+                1.0 + (fract(bands * 0.5) * 3.0).floor() + light
+            }
+            PlanetKind::Dry => {
+                let mut mixed = n.y * d_to_light;
+                if dith {
+                    mixed *= 0.95;
+                }
+                return (mixed * 15.0).floor() / 7.0;
+            }
+        };
+
+        k.min(7.0) / 7.0
+    }
+}
+
+/// Lit side to dark side in four steps.
+fn light_level(d_to_light: f32, dith: bool) -> f32 {
+    let mut light = (1.0 - d_to_light).clamp(0.0, 1.0);
+    if dith {
+        light *= 0.95;
+    }
+    (light * 3.99).floor()
+}
+
+fn rotate(vec: Vec2, angle: f32) -> Vec2 {
+    let vec = vec - 0.5;
+    let (sin, cos) = angle.sin_cos();
+    // GLSL's `vec * mat2(...)` multiplies by the transpose
+    Vec2::new(vec.x * cos - vec.y * sin, vec.x * sin + vec.y * cos) + 0.5
+}
+
+fn spherify(uv: Vec2) -> Vec2 {
+    let centered = uv * 2.0 - 1.0;
+    let z = (1.0 - centered.dot(centered)).sqrt();
+    let sphere = centered / (z + 1.0);
+
+    sphere * 0.5 + 0.5
+}
+
+impl Fragment for Planets<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let shader = &self.shader;
+        let material = shader.material;
+
+        // pixelzing and dithering
+        let mut uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // distance from center, to create a circle
+        let d_to_center = uv.distance(Vec2::splat(0.5));
+        // the circle mesh never rasterises these, and `spherify` is NaN out there
+        if d_to_center > 0.5 {
+            return Vec4::ZERO;
+        }
+        uv = spherify(uv);
+
+        // distance from light source, to create shading
+        let mut d_to_light = uv.distance(material.light_origin);
+        // bit of contrast
+        d_to_light += (d_to_center * 3.0).powf(4.0) * 0.05;
+
+        // the surface turns under the light, which stays put
+        let surface_uv = shader.turn(uv);
+
+        // choose and apply colors
+        let n = self
+            .cells
+            .get_or_insert_with(vertex_uv, || shader.surface(surface_uv));
+        let col_val = shader.col_value(n, surface_uv, d_to_light, dith);
+        let col = self.colors.sample(Vec2::new(col_val, 0.0)).truncate();
+
+        // apply alpha
+        let a = step(d_to_center, 0.5);
+        col.extend(a)
+    }
+}
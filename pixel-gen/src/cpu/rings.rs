@@ -0,0 +1,66 @@
+//! Port of `assets/shaders/rings.glsl`.
+
+use super::{
+    glsl::{dither, fract, step},
+    Fragment, Texture,
+};
+use crate::{rings::RingMaterial, *};
+
+pub struct Rings<'a> {
+    material: &'a RingMaterial,
+    colors: Texture<'a>,
+}
+
+impl<'a> Rings<'a> {
+    pub fn new(material: &'a RingMaterial, images: &'a Assets<Image>) -> Option<Self> {
+        Some(Rings {
+            material,
+            colors: Texture::new(images, &material.color_texture)?,
+        })
+    }
+
+    fn rand(&self, coord: Vec2) -> f32 {
+        fract(coord.dot(Vec2::new(12.9898, 78.233)).sin() * 15.5453 * self.material.seed)
+    }
+}
+
+impl Fragment for Rings<'_> {
+    fn fragment(&mut self, vertex_uv: Vec2) -> Vec4 {
+        let material = self.material;
+
+        // pixelizing and dithering
+        let uv = (vertex_uv * material.pixels).floor() / material.pixels;
+        let dith = dither(vertex_uv, uv, material.pixels);
+
+        // undo the tilt and the squash, so the ring is a circle of radius 1
+        let p = (uv - 0.5) * 2.0;
+        let (sin, cos) = material.tilt.sin_cos();
+        let mut p = Vec2::new(cos * p.x + sin * p.y, -sin * p.x + cos * p.y);
+        p.y /= material.inclination;
+        let r = p.length();
+
+        // the far half goes behind the planet, the near half in front of it
+        let mut a = step(material.inner, r) * step(r, 1.0);
+        if material.front == 1 {
+            a *= step(p.y, 0.0);
+        } else {
+            a *= 1.0 - step(p.y, 0.0);
+        }
+
+        // concentric bands of varying brightness, with a few gaps
+        let band = self.rand(Vec2::new(
+            ((r - material.inner) / (1.0 - material.inner) * 12.0).floor(),
+            1.0,
+        ));
+        if band < 0.15 {
+            a = 0.0;
+        }
+        let mut k = 3.0 + (band * 3.99).floor();
+        if dith && fract(band * 7.0) < 0.5 {
+            k -= 1.0;
+        }
+
+        let col = self.colors.sample(Vec2::new(k / 7.0, 0.0)).truncate();
+        col.extend(a)
+    }
+}
@@ -0,0 +1,611 @@
+//! Renders a seamlessly looping animation of a generated scene without a
+//! window or a GPU, and writes it as a GIF, an APNG or a spritesheet.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Serialize;
+
+use crate::{
+    cpu::Frame,
+    headless::{RenderError, Scene},
+    shaders::{cycle, AnimatedMaterialConfig, Tracks},
+    *,
+};
+
+/// What an exported loop is written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A GIF, every frame indexing one shared palette.
+    Gif,
+    /// An animated PNG, in full colour with straight alpha.
+    Apng,
+    /// Every frame in a grid on one PNG, described by a TOML file beside it.
+    Sheet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gif" => Ok(ExportFormat::Gif),
+            "apng" => Ok(ExportFormat::Apng),
+            "sheet" => Ok(ExportFormat::Sheet),
+            _ => Err(format!("unknown export format: {s}")),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// The format a `.gif` or, as an APNG, a `.png` file is written in.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gif" => Some(ExportFormat::Gif),
+            "png" | "apng" => Some(ExportFormat::Apng),
+            _ => None,
+        }
+    }
+}
+
+/// How long an exported loop is and how it is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    /// Frames in the loop; the one after the last would be the first again.
+    pub frames: u32,
+    /// Seconds the loop lasts. Every animation is sped up or slowed down to
+    /// come back round a whole number of times in it.
+    pub seconds: f32,
+    pub format: ExportFormat,
+    /// Frames across each row of a spritesheet, as close to square as the
+    /// frame count allows if `None`.
+    pub columns: Option<u32>,
+}
+
+impl Export {
+    pub const DEFAULT: Export = Export {
+        frames: 48,
+        seconds: 8.0,
+        format: ExportFormat::Gif,
+        columns: None,
+    };
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How much faster or slower than its own pace a layer may play to fit a
+/// loop before [`render_loop`] reports it.
+pub const MAX_STRETCH: f32 = 1.5;
+
+/// Renders the loop of the scene `options` generates and writes it to
+/// `output`, at the size of `options.screen_size`. Returns the layers whose
+/// pace the loop changed by more than [`MAX_STRETCH`].
+///
+/// Each frame is handed on as soon as it is drawn: an APNG is written as it
+/// goes, and a GIF or a spritesheet keeps only its frames' sRGB bytes.
+pub fn export(
+    options: &Options,
+    assets: &Path,
+    export: &Export,
+    output: &Path,
+) -> Result<Vec<Stretch>, RenderError> {
+    let size = options.screen_size.vec2().as_uvec2();
+    let (frames, seconds) = (export.frames, export.seconds);
+
+    match export.format {
+        ExportFormat::Gif => {
+            let mut pixels = Vec::with_capacity(frames as usize);
+            let stretched = render_loop(options, assets, frames, seconds, |frame| {
+                pixels.push(frame.to_srgba8());
+                Ok(())
+            })?;
+            write_gif(size, &pixels, seconds, output)?;
+            Ok(stretched)
+        }
+        ExportFormat::Apng => {
+            let mut apng = Apng::create(size, frames, seconds, output)?;
+            let stretched = render_loop(options, assets, frames, seconds, |frame| {
+                apng.write_frame(&frame)
+            })?;
+            apng.finish()?;
+            Ok(stretched)
+        }
+        ExportFormat::Sheet => {
+            let mut sheet = Sheet::new(size, frames, export.columns);
+            let mut i = 0;
+            let stretched = render_loop(options, assets, frames, seconds, |frame| {
+                sheet.place(i, &frame);
+                i += 1;
+                Ok(())
+            })?;
+            sheet.write(seconds / frames as f32, output)?;
+            Ok(stretched)
+        }
+    }
+}
+
+/// A layer whose animation a loop plays at a different pace than its own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stretch {
+    pub layer: &'static str,
+    /// Seconds the animation takes to come back round at its own pace; the
+    /// one furthest from the loop's, for layers with several.
+    pub period: f32,
+    /// How many times as fast the loop plays it.
+    pub speed: f32,
+}
+
+/// Renders `frames` evenly spaced frames of the scene `options` generates, as
+/// it animates over `seconds`, handing each to `each` in turn. Returns the
+/// layers sped up or slowed down by more than [`MAX_STRETCH`] to fit.
+pub fn render_loop(
+    options: &Options,
+    assets: &Path,
+    frames: u32,
+    seconds: f32,
+    mut each: impl FnMut(Frame) -> Result<(), RenderError>,
+) -> Result<Vec<Stretch>, RenderError> {
+    let mut scene = Scene::generate(options, assets)?;
+    let motion = Motion::new(options, &scene, seconds);
+
+    for i in 0..frames {
+        motion.pose(&mut scene, seconds * i as f32 / frames as f32);
+        each(scene.draw(options)?)?;
+    }
+    Ok(motion.stretched)
+}
+
+/// The tracks a loop plays, fitted to it: the nebulae and dust flowing, the
+/// planets turning and the big stars twinkling, as they do with `animate`.
+/// Everything else holds still, as zooming, drifting, the light circling and
+/// the black hole spinning take too long to come back round.
+struct Motion {
+    nebulae: Tracks,
+    dust: Tracks,
+    stars: Vec<Tracks>,
+    planets: Vec<Tracks>,
+    stretched: Vec<Stretch>,
+}
+
+impl Motion {
+    fn new(options: &Options, scene: &Scene, seconds: f32) -> Self {
+        let mut stretched: Vec<Stretch> = Vec::new();
+        let mut fitted = |layer: &'static str, mut tracks: Tracks| {
+            let periods: Vec<f32> = tracks.iter().map(|(_, a)| a.duration).collect();
+            tracks.fit(seconds);
+            for (period, (_, animation)) in periods.into_iter().zip(tracks.iter()) {
+                let speed = period / animation.duration;
+                let far = |speed: f32| speed.max(1. / speed);
+                if far(speed) <= MAX_STRETCH {
+                    continue;
+                }
+                match stretched.iter_mut().find(|s| s.layer == layer) {
+                    Some(worst) if far(worst.speed) >= far(speed) => {}
+                    Some(worst) => {
+                        *worst = Stretch {
+                            layer,
+                            period,
+                            speed,
+                        }
+                    }
+                    None => stretched.push(Stretch {
+                        layer,
+                        period,
+                        speed,
+                    }),
+                }
+            }
+            tracks
+        };
+        let flow = |period| Tracks::new().with("time", cycle(period));
+        let nebulae = fitted("nebulae", flow(options.nebulae_settings.flow_period));
+        let dust = fitted("dust", flow(options.dust_settings.flow_period));
+        let stars = scene
+            .stars
+            .iter()
+            .map(|star| fitted("stars", star.twinkle.clone()))
+            .collect();
+        let planets = scene
+            .planets
+            .iter()
+            .map(|planet| fitted("planets", flow(planet.rotation_period)))
+            .collect();
+
+        Motion {
+            nebulae,
+            dust,
+            stars,
+            planets,
+            stretched,
+        }
+    }
+
+    /// Sets every animated uniform in `scene` to its value `seconds` in.
+    fn pose(&self, scene: &mut Scene, seconds: f32) {
+        if let Some(material) = &mut scene.nebulae {
+            pose(material, &self.nebulae, seconds);
+        }
+        if let Some(material) = &mut scene.star_stuff {
+            pose(material, &self.dust, seconds);
+        }
+        for (star, tracks) in scene.stars.iter_mut().zip(&self.stars) {
+            pose(&mut star.material, tracks, seconds);
+        }
+        for (planet, tracks) in scene.planets.iter_mut().zip(&self.planets) {
+            pose(&mut planet.material, tracks, seconds);
+        }
+    }
+}
+
+fn pose(material: &mut impl AnimatedMaterial2D, tracks: &Tracks, seconds: f32) {
+    let mut tracks = tracks.clone();
+    tracks.advance(seconds);
+    for (track, value) in tracks.values() {
+        material.update(track, value);
+    }
+}
+
+/// Writes `frames` of sRGB RGBA bytes, `size` pixels each, to `output` as a
+/// GIF looping forever every `seconds`. A GIF counts in hundredths, so each
+/// frame is shown for a whole number of them, spread by [`frame_delays`].
+///
+/// Every frame indexes the same palette: the colours the frames use, which
+/// fit unless blending mixed too many new ones, or else the 256 closest to
+/// them, so no colour flickers from one frame to the next. Pixels less than
+/// half opaque are left transparent.
+pub fn write_gif(
+    size: UVec2,
+    frames: &[Vec<u8>],
+    seconds: f32,
+    output: &Path,
+) -> Result<(), RenderError> {
+    let encode_error = |message: String| RenderError::Encode {
+        path: output.to_path_buf(),
+        message,
+    };
+    if frames.is_empty() {
+        return Err(encode_error("no frames to write".into()));
+    }
+    let (Ok(width), Ok(height)) = (u16::try_from(size.x), u16::try_from(size.y)) else {
+        return Err(encode_error(format!(
+            "{}x{} is too large for a GIF",
+            size.x, size.y
+        )));
+    };
+
+    let palette = GifPalette::new(frames);
+
+    let file = File::create(output).map_err(|err| encode_error(err.to_string()))?;
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &palette.colors)
+        .map_err(|err| encode_error(err.to_string()))?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|err| encode_error(err.to_string()))?;
+
+    for (pixels, delay) in frames.iter().zip(frame_delays(frames.len(), seconds, 100)) {
+        let frame = gif::Frame {
+            width,
+            height,
+            delay,
+            // clear each frame first, or transparent pixels show the last one
+            dispose: gif::DisposalMethod::Background,
+            transparent: palette.transparent,
+            buffer: Cow::Owned(palette.indices(pixels)),
+            ..default()
+        };
+        encoder
+            .write_frame(&frame)
+            .map_err(|err| encode_error(err.to_string()))?;
+    }
+    Ok(())
+}
+
+/// How long to show each of `frames` for, in `ticks` a second, spread so they
+/// add up to `seconds` rather than drifting from it: each frame ends at its
+/// share of the loop, rounded.
+fn frame_delays(frames: usize, seconds: f32, ticks: u32) -> Vec<u16> {
+    let total = (seconds * ticks as f32).round() as usize;
+    let end = |i: usize| (total * i + frames / 2) / frames;
+    (0..frames).map(|i| (end(i + 1) - end(i)) as u16).collect()
+}
+
+/// The one palette every frame of a GIF indexes.
+struct GifPalette {
+    /// RGB triples, at most 256 of them.
+    colors: Vec<u8>,
+    transparent: Option<u8>,
+    exact: HashMap<[u8; 3], u8>,
+    /// The closest colours, when the frames use too many to index exactly.
+    quantized: Option<color_quant::NeuQuant>,
+}
+
+impl GifPalette {
+    /// Pixels sampled to pick the closest colours from; plenty for a scene
+    /// drawn from a handful of palette colours.
+    const SAMPLES: usize = 1 << 20;
+
+    /// The palette for `frames` of sRGB RGBA bytes.
+    fn new(frames: &[Vec<u8>]) -> Self {
+        let pixels = || frames.iter().flat_map(|frame| frame.chunks_exact(4));
+        let has_transparent = pixels().any(|pixel| pixel[3] < 128);
+        let space = 256 - has_transparent as usize;
+
+        let mut exact = HashMap::new();
+        for pixel in pixels().filter(|pixel| pixel[3] >= 128) {
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            if !exact.contains_key(&rgb) {
+                if exact.len() == space {
+                    return Self::quantized(frames, space, has_transparent);
+                }
+                exact.insert(rgb, exact.len() as u8);
+            }
+        }
+
+        let mut colors = vec![0; exact.len() * 3];
+        for (rgb, &index) in &exact {
+            colors[index as usize * 3..][..3].copy_from_slice(rgb);
+        }
+        let transparent = has_transparent.then(|| {
+            colors.extend([0; 3]);
+            exact.len() as u8
+        });
+        GifPalette {
+            colors,
+            transparent,
+            exact,
+            quantized: None,
+        }
+    }
+
+    fn quantized(frames: &[Vec<u8>], space: usize, has_transparent: bool) -> Self {
+        let total = frames.iter().map(|frame| frame.len() / 4).sum::<usize>();
+        let step = (total / Self::SAMPLES).max(1);
+        let samples: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| frame.chunks_exact(4))
+            .step_by(step)
+            .filter(|pixel| pixel[3] >= 128)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect();
+
+        let quantized = color_quant::NeuQuant::new(10, space, &samples);
+        let mut colors = quantized.color_map_rgb();
+        let transparent = has_transparent.then(|| {
+            colors.extend([0; 3]);
+            (colors.len() / 3 - 1) as u8
+        });
+        GifPalette {
+            colors,
+            transparent,
+            exact: HashMap::new(),
+            quantized: Some(quantized),
+        }
+    }
+
+    /// Indexes a frame of sRGB RGBA bytes.
+    fn indices(&self, pixels: &[u8]) -> Vec<u8> {
+        pixels
+            .chunks_exact(4)
+            .map(|pixel| match (self.transparent, &self.quantized) {
+                (Some(transparent), _) if pixel[3] < 128 => transparent,
+                (_, Some(quantized)) => {
+                    quantized.index_of(&[pixel[0], pixel[1], pixel[2], 255]) as u8
+                }
+                (_, None) => self.exact[&[pixel[0], pixel[1], pixel[2]]],
+            })
+            .collect()
+    }
+}
+
+/// An animated PNG looping forever, written a frame at a time.
+pub struct Apng {
+    writer: png::Writer<BufWriter<File>>,
+    delays: std::vec::IntoIter<u16>,
+    path: PathBuf,
+}
+
+impl Apng {
+    /// Starts an APNG of `frames` frames, `size` pixels each, at `output`,
+    /// lasting `seconds`: each frame is shown for a whole number of
+    /// milliseconds, spread by [`frame_delays`].
+    pub fn create(
+        size: UVec2,
+        frames: u32,
+        seconds: f32,
+        output: &Path,
+    ) -> Result<Self, RenderError> {
+        let encode_error = |message: String| RenderError::Encode {
+            path: output.to_path_buf(),
+            message,
+        };
+        if frames == 0 {
+            return Err(encode_error("no frames to write".into()));
+        }
+
+        let file = File::create(output).map_err(|err| encode_error(err.to_string()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), size.x, size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        // 0 plays forever
+        encoder
+            .set_animated(frames, 0)
+            .map_err(|err| encode_error(err.to_string()))?;
+        let writer = encoder
+            .write_header()
+            .map_err(|err| encode_error(err.to_string()))?;
+
+        Ok(Apng {
+            writer,
+            delays: frame_delays(frames as usize, seconds, 1000).into_iter(),
+            path: output.to_path_buf(),
+        })
+    }
+
+    /// Writes the next frame, which is the size the APNG was created with.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), RenderError> {
+        let delay = self.delays.next().unwrap_or_default();
+        self.writer
+            .set_frame_delay(delay, 1000)
+            .and_then(|_| self.writer.write_image_data(&frame.to_srgba8()))
+            .map_err(|err| self.encode_error(err))
+    }
+
+    /// Finishes the file, once every frame is written.
+    pub fn finish(self) -> Result<(), RenderError> {
+        let path = self.path;
+        self.writer.finish().map_err(|err| RenderError::Encode {
+            path,
+            message: err.to_string(),
+        })
+    }
+
+    fn encode_error(&self, err: png::EncodingError) -> RenderError {
+        RenderError::Encode {
+            path: self.path.clone(),
+            message: err.to_string(),
+        }
+    }
+}
+
+/// Where a spritesheet's frames are, written next to it.
+#[derive(Serialize)]
+struct SheetInfo {
+    /// The spritesheet's file name.
+    image: String,
+    frame_width: u32,
+    frame_height: u32,
+    columns: u32,
+    rows: u32,
+    /// How long each frame is shown for.
+    frame_milliseconds: u32,
+    /// In the order they play, left to right then top to bottom.
+    frame: Vec<SheetFrame>,
+}
+
+#[derive(Serialize)]
+struct SheetFrame {
+    /// Top left corner, in pixels from the top left of the sheet.
+    x: u32,
+    y: u32,
+}
+
+/// A spritesheet of a loop's frames in a grid, as sRGB RGBA bytes.
+pub struct Sheet {
+    /// The size of a frame.
+    size: UVec2,
+    count: u32,
+    columns: u32,
+    rows: u32,
+    pixels: Vec<u8>,
+}
+
+impl Sheet {
+    /// An empty sheet for `count` frames of `size` pixels, `columns` to a
+    /// row, as close to square as the count allows if `None`.
+    pub fn new(size: UVec2, count: u32, columns: Option<u32>) -> Self {
+        let columns = columns
+            .unwrap_or_else(|| (count as f32).sqrt().ceil() as u32)
+            .clamp(1, count.max(1));
+        let rows = count.div_ceil(columns);
+        let pixels = (columns * size.x * rows * size.y) as usize * 4;
+        Sheet {
+            size,
+            count,
+            columns,
+            rows,
+            pixels: vec![0; pixels],
+        }
+    }
+
+    fn corner(&self, i: u32) -> UVec2 {
+        UVec2::new(i % self.columns, i / self.columns) * self.size
+    }
+
+    /// Copies `frame` into the `i`th place, left to right then top to bottom.
+    pub fn place(&mut self, i: u32, frame: &Frame) {
+        let corner = self.corner(i);
+        let stride = (self.columns * self.size.x) as usize * 4;
+        let row = self.size.x as usize * 4;
+        let srgba = frame.to_srgba8();
+        for (y, from) in srgba.chunks_exact(row).enumerate() {
+            let start = (corner.y as usize + y) * stride + corner.x as usize * 4;
+            self.pixels[start..][..row].copy_from_slice(from);
+        }
+    }
+
+    /// Writes the sheet to `output` as one PNG, and describes the grid in a
+    /// TOML file of the same name, for engines to cut the frames back out,
+    /// each shown for `delay` seconds.
+    pub fn write(self, delay: f32, output: &Path) -> Result<(), RenderError> {
+        let encode_error = |path: &Path, message: String| RenderError::Encode {
+            path: path.to_path_buf(),
+            message,
+        };
+        if self.count == 0 {
+            return Err(encode_error(output, "no frames to write".into()));
+        }
+
+        image::save_buffer_with_format(
+            output,
+            &self.pixels,
+            self.columns * self.size.x,
+            self.rows * self.size.y,
+            image::ExtendedColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|err| encode_error(output, err.to_string()))?;
+
+        let info = SheetInfo {
+            image: output
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            frame_width: self.size.x,
+            frame_height: self.size.y,
+            columns: self.columns,
+            rows: self.rows,
+            frame_milliseconds: (delay * 1000.).round() as u32,
+            frame: (0..self.count)
+                .map(|i| {
+                    let corner = self.corner(i);
+                    SheetFrame {
+                        x: corner.x,
+                        y: corner.y,
+                    }
+                })
+                .collect(),
+        };
+        let path = output.with_extension("toml");
+        let toml = toml::to_string(&info).map_err(|err| encode_error(&path, err.to_string()))?;
+        fs::write(&path, toml).map_err(|err| encode_error(&path, err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_delays_add_up_to_the_loop() {
+        // 8 / 48 seconds is 16.67 hundredths, which rounds to 17 every frame
+        let delays = frame_delays(48, 8., 100);
+        assert_eq!(delays.iter().map(|&d| d as u32).sum::<u32>(), 800);
+        assert!(delays.iter().all(|&d| d == 16 || d == 17));
+
+        // and 166.67 milliseconds to 167
+        let delays = frame_delays(48, 8., 1000);
+        assert_eq!(delays.iter().map(|&d| d as u32).sum::<u32>(), 8000);
+
+        assert_eq!(frame_delays(4, 1., 100), [25, 25, 25, 25]);
+    }
+}
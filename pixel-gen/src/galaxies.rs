@@ -0,0 +1,195 @@
+use std::{f32::consts::TAU, ops::Range};
+
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    *,
+};
+
+#[derive(Component)]
+pub struct Galaxy;
+
+#[derive(SystemParam)]
+pub struct GalaxySpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    mats: ResMut<'w, Assets<GalaxyMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+impl BackgroundLayer for Galaxy {
+    const NAME: &'static str = "galaxies";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyG);
+
+    type Param = GalaxySpawner<'static>;
+
+    fn spawn(commands: &mut Commands, options: &config::ResolvedOptions, sg: &mut GalaxySpawner) {
+        for material in generate(options, options.palette(), &mut sg.images) {
+            let layer = ParallaxLayer::new(
+                options.parallax_settings.galaxies,
+                material.position,
+                options.screen_size.rect(),
+            )
+            .wrapping(material.position.truncate(), material.radius);
+            let mesh = MaterialMesh2dBundle {
+                mesh: sg
+                    .meshes
+                    .add(Rectangle::from_length(material.radius * 2.))
+                    .into(),
+                material: sg.mats.add(material),
+                // far away, but still drawn over the nebulae and dust
+                transform: Transform::from_translation(Vec3::ZERO.with_z(options.layer_z.galaxies)),
+                ..default()
+            };
+
+            commands.spawn((mesh, Galaxy, layer));
+        }
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.galaxies
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.galaxies = enabled;
+    }
+}
+
+/// Materials of every galaxy for `options`, drawn from the "galaxies" seed stream.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> Vec<GalaxyMaterial> {
+    let mut rng = options.seed.stream("galaxies");
+    let color_gradiant = images.add(palette.gradient_image_with_bg().0);
+
+    let galaxies = (0..config::sample(&mut rng, &options.galaxy_settings.count))
+        .map(|_| {
+            let position = options.screen_size.random_postion(&mut rng, 1.25);
+            GalaxyMaterial::new(options, &mut rng, position, color_gradiant.clone())
+        })
+        .collect();
+
+    tile::wrapped(options, galaxies, quad, |galaxy, shift| {
+        galaxy.position += shift.extend(0.)
+    })
+}
+
+/// Shape of the distant galaxies.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct GalaxySettings {
+    pub count: Range<u32>,
+    /// Radius of the disk in screen pixels.
+    pub radius: Range<f32>,
+    /// Chance a galaxy is a featureless ellipse rather than a spiral.
+    pub elliptical: f32,
+    pub arms: Range<i32>,
+    /// How far the arms wind round, in radians from the core to the rim.
+    pub twist: Range<f32>,
+    /// Radius of the bright core, as a fraction of the disk.
+    pub bulge: Range<f32>,
+    /// How far the disk is tipped away from face on, in degrees.
+    pub tilt: Range<f32>,
+}
+
+impl GalaxySettings {
+    pub const DEFAULT: GalaxySettings = GalaxySettings {
+        count: 0..3,
+        radius: 40.0..100.0,
+        elliptical: 0.25,
+        arms: 2..5,
+        twist: 2.0..6.0,
+        bulge: 0.15..0.35,
+        tilt: 0.0..70.0,
+    };
+}
+
+impl Default for GalaxySettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// World space covered by a galaxy's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &GalaxyMaterial) -> Rect {
+    Rect::from_center_size(
+        material.position.truncate(),
+        Vec2::splat(material.radius * 2.),
+    )
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct GalaxyMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: f32,
+    #[uniform(1)]
+    pub(crate) seed: f32,
+    #[uniform(2)]
+    pub(crate) arms: i32,
+    #[uniform(3)]
+    pub(crate) twist: f32,
+    #[uniform(4)]
+    pub(crate) bulge: f32,
+    /// Height of the disk over its width, `cos` of the tilt.
+    #[uniform(5)]
+    pub(crate) tilt: f32,
+    #[uniform(6)]
+    pub(crate) rotation: f32,
+    #[uniform(7)]
+    pub(crate) elliptical: i32,
+
+    #[texture(8)]
+    #[sampler(9)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(10)]
+    pub(crate) position: Vec3,
+
+    /// Radius in world space, for the mesh.
+    pub(crate) radius: f32,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl GalaxyMaterial {
+    fn new(
+        options: &config::Options,
+        rng: &mut impl Rng,
+        position: Vec3,
+        color_gradiant: Handle<Image>,
+    ) -> Self {
+        let settings = &options.galaxy_settings;
+        let radius = config::sample(rng, &settings.radius);
+        GalaxyMaterial {
+            // two screen pixels per galaxy pixel, like the big stars
+            pixels: radius.max(1.).floor(),
+            seed: rng.gen_range(0.0..100.0),
+            arms: config::sample(rng, &settings.arms).max(1),
+            twist: config::sample(rng, &settings.twist),
+            bulge: config::sample(rng, &settings.bulge).max(0.01),
+            // never quite edge on, or the disk vanishes
+            tilt: config::sample(rng, &settings.tilt)
+                .to_radians()
+                .cos()
+                .max(0.1),
+            rotation: rng.gen_range(0.0..TAU),
+            elliptical: (rng.gen::<f32>() < settings.elliptical) as i32,
+            color_texture: Some(color_gradiant),
+            position,
+            radius,
+            blend: options.blend_modes.galaxies,
+        }
+    }
+}
+
+impl ParallaxMaterial2D for GalaxyMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
+    }
+}
+
+layers::blended_material!(GalaxyMaterial, "galaxies");
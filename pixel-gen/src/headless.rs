@@ -0,0 +1,368 @@
+//! Renders a generated scene to an image without a window or a GPU.
+
+use std::{
+    env,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::io::file::FileAssetReader,
+    color::ColorToComponents,
+    render::{
+        render_asset::RenderAssetUsages,
+        texture::{CompressedImageFormats, ImageSampler, ImageType, TextureError},
+    },
+};
+
+use crate::{
+    cpu::{Fragment, Frame},
+    *,
+};
+
+#[derive(Debug)]
+pub enum RenderError {
+    ReadAsset { path: PathBuf, source: io::Error },
+    DecodeAsset { path: PathBuf, source: TextureError },
+    MissingTexture(&'static str),
+    UnknownColorScheme(ColorScheme),
+    Encode { path: PathBuf, message: String },
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::ReadAsset { path, source } => write!(
+                f,
+                "could not read {}: {source} (pass --assets <DIR> to point at the assets directory)",
+                path.display()
+            ),
+            RenderError::DecodeAsset { path, source } => {
+                write!(f, "could not decode {}: {source}", path.display())
+            }
+            RenderError::MissingTexture(layer) => write!(f, "{layer} has no texture to sample"),
+            RenderError::UnknownColorScheme(scheme) => write!(f, "unknown colorscheme {scheme}"),
+            RenderError::Encode { path, message } => {
+                write!(f, "could not write {}: {message}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// The first `assets` directory found beside the crate the `AssetServer`
+/// would load from, in the working directory, or beside the executable.
+pub fn assets_dir() -> PathBuf {
+    let candidates = [
+        Some(FileAssetReader::get_base_path()),
+        env::current_dir().ok(),
+        env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf)),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("assets"))
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from("assets"))
+}
+
+/// Renders the scene `options` generates, at the size of `options.screen_size`.
+///
+/// Layers are drawn in the order the 2d transparent pass sorts the spawned
+/// meshes, by `options.layer_z`, over the background: by default nebulae,
+/// dust, big stars, galaxies, asteroids, the black hole, then planets, each
+/// with its back ring half, atmosphere and front ring half.
+pub fn render(options: &Options, assets: &Path) -> Result<Frame, RenderError> {
+    Scene::generate(options, assets)?.draw(options)
+}
+
+/// Like [`render`], with a rim of pixels one past every edge of the screen,
+/// drawn as if the scene carried on past it. A tile's rim is the far edge of
+/// the next tile over, which is what [`find_seams`] checks it against.
+pub fn render_with_rim(options: &Options, assets: &Path) -> Result<Frame, RenderError> {
+    Scene::generate(options, assets)?.draw_with_rim(options, 1)
+}
+
+/// The materials of every layer `options` generates, along with the images
+/// they sample, so the same scene can be drawn again once they change.
+pub(crate) struct Scene {
+    images: Assets<Image>,
+    background: Option<Vec4>,
+    pub(crate) nebulae: Option<NebulaeMaterial>,
+    pub(crate) star_stuff: Option<StarStuffMaterial>,
+    pub(crate) stars: Vec<stars::BigStar>,
+    galaxies: Vec<GalaxyMaterial>,
+    asteroids: Vec<AsteroidMaterial>,
+    black_hole: Vec<BlackHoleMaterial>,
+    pub(crate) planets: Vec<planets::Planet>,
+}
+
+impl Scene {
+    pub(crate) fn generate(options: &Options, assets: &Path) -> Result<Scene, RenderError> {
+        let mut color_schemes = ColorSchemes::default();
+        let dir = assets.join("colorschemes");
+        if let Err(err) = color_schemes.load_dir(&dir) {
+            warn!("could not read palette dir {}: {err}", dir.display());
+        }
+        let palette = color_schemes
+            .get(&options.colorscheme)
+            .ok_or_else(|| RenderError::UnknownColorScheme(options.colorscheme.clone()))?;
+
+        let mut images = Assets::<Image>::default();
+        let background =
+            (!options.transparency).then(|| LinearRgba::from(background::color(palette)).to_vec4());
+
+        let nebulae = options
+            .nebulae
+            .then(|| nebulae::generate(options, palette, &mut images));
+        let star_stuff = options
+            .dust
+            .then(|| star_stuff::generate(options, palette, &mut images));
+        let stars = if options.stars {
+            let sheet = load_image(&assets.join(stars::STAR_SHEET))?;
+            let sheet = images.add(sheet);
+            stars::generate(options, palette, &mut images, sheet)
+        } else {
+            Vec::new()
+        };
+        let galaxies = if options.galaxies {
+            galaxies::generate(options, palette, &mut images)
+        } else {
+            Vec::new()
+        };
+        let asteroids = if options.asteroids {
+            asteroids::generate(options, palette, &mut images)
+        } else {
+            Vec::new()
+        };
+        let black_hole = if options.black_hole {
+            black_hole::generate(options, palette, &mut images).0
+        } else {
+            Vec::new()
+        };
+        let planets = if options.planets {
+            planets::generate(options, palette, &mut images)
+        } else {
+            Vec::new()
+        };
+
+        Ok(Scene {
+            images,
+            background,
+            nebulae,
+            star_stuff,
+            stars,
+            galaxies,
+            asteroids,
+            black_hole,
+            planets,
+        })
+    }
+
+    /// Draws the layers as they are now, see [`render`].
+    pub(crate) fn draw(&self, options: &Options) -> Result<Frame, RenderError> {
+        self.draw_with_rim(options, 0)
+    }
+
+    /// Draws the layers `rim` pixels past every edge of the screen, see
+    /// [`render_with_rim`]: the layers covering the screen carry on past it,
+    /// and anything else is only there if it (or a tile copy) reaches it.
+    fn draw_with_rim(&self, options: &Options, rim: u32) -> Result<Frame, RenderError> {
+        let size = options.screen_size.vec2().as_uvec2() + 2 * rim;
+        let bleed = rim as f32;
+        let mut frame = Frame::new(size);
+
+        if let Some(background) = self.background {
+            frame.draw(frame.world_rect(), &mut |_| background);
+        }
+
+        let z = &options.layer_z;
+        let modes = &options.blend_modes;
+        let images = &self.images;
+        // each entry draws a whole layer, or one planet with its rings and
+        // atmosphere, and keeps its place among those with the same z
+        let mut layers: Vec<(f32, Box<LayerDraw>)> = Vec::new();
+
+        if let Some(material) = &self.nebulae {
+            layers.push((
+                z.nebulae,
+                Box::new(move |frame| {
+                    let shader = cpu::Nebulae::new(material, images);
+                    let quad = nebulae::quad(options);
+                    draw_bleeding(frame, quad, bleed, shader, modes.nebulae, "nebulae")
+                }),
+            ));
+        }
+        if let Some(material) = &self.star_stuff {
+            layers.push((
+                z.dust,
+                Box::new(move |frame| {
+                    let shader = cpu::StarStuff::new(material, images);
+                    let quad = star_stuff::quad(options);
+                    draw_bleeding(frame, quad, bleed, shader, modes.dust, "dust")
+                }),
+            ));
+        }
+        layers.push((
+            z.stars,
+            Box::new(|frame| {
+                for star in &self.stars {
+                    let shader = cpu::BigStar::new(&star.material, images);
+                    let quad = stars::quad(&star.material);
+                    draw(frame, quad, shader, modes.stars, "stars")?;
+                }
+                Ok(())
+            }),
+        ));
+        layers.push((
+            z.galaxies,
+            Box::new(|frame| {
+                for material in &self.galaxies {
+                    let shader = cpu::Galaxies::new(material, images);
+                    let quad = galaxies::quad(material);
+                    draw(frame, quad, shader, modes.galaxies, "galaxies")?;
+                }
+                Ok(())
+            }),
+        ));
+        layers.push((
+            z.asteroids,
+            Box::new(|frame| {
+                for material in &self.asteroids {
+                    let shader = cpu::Asteroids::new(material, images);
+                    let quad = asteroids::quad(material);
+                    draw(frame, quad, shader, modes.asteroids, "asteroids")?;
+                }
+                Ok(())
+            }),
+        ));
+        layers.push((
+            z.black_hole,
+            Box::new(|frame| {
+                for material in &self.black_hole {
+                    let shader = cpu::BlackHole::new(material, images);
+                    let quad = black_hole::quad(material);
+                    draw(frame, quad, shader, modes.black_hole, "black hole")?;
+                }
+                Ok(())
+            }),
+        ));
+        for (i, planet) in self.planets.iter().enumerate() {
+            layers.push((
+                z.planets + i as f32 * 0.01,
+                Box::new(move |frame| draw_planet(frame, planet, images, modes.planets)),
+            ));
+        }
+
+        layers.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        for (_, layer) in layers {
+            layer(&mut frame)?;
+        }
+
+        Ok(frame)
+    }
+}
+
+/// Renders the scene and writes it to `output` as a PNG, with an alpha
+/// channel when `options.transparency` is set.
+pub fn render_to_file(options: &Options, assets: &Path, output: &Path) -> Result<(), RenderError> {
+    write_png(&render(options, assets)?, output)
+}
+
+/// Writes a rendered frame to `output` as a PNG.
+pub fn write_png(frame: &Frame, output: &Path) -> Result<(), RenderError> {
+    let encode_error = |message: String| RenderError::Encode {
+        path: output.to_path_buf(),
+        message,
+    };
+
+    frame
+        .to_image()
+        .try_into_dynamic()
+        .map_err(|err| encode_error(err.to_string()))?
+        .save_with_format(output, image::ImageFormat::Png)
+        .map_err(|err| encode_error(err.to_string()))
+}
+
+type LayerDraw<'a> = dyn FnOnce(&mut Frame) -> Result<(), RenderError> + 'a;
+
+fn draw(
+    frame: &mut Frame,
+    quad: Rect,
+    shader: Option<impl Fragment>,
+    mode: BlendMode,
+    layer: &'static str,
+) -> Result<(), RenderError> {
+    draw_bleeding(frame, quad, 0., shader, mode, layer)
+}
+
+/// [`draw`], carrying on `bleed` pixels past the quad, see
+/// [`Frame::draw_bleeding`].
+fn draw_bleeding(
+    frame: &mut Frame,
+    quad: Rect,
+    bleed: f32,
+    shader: Option<impl Fragment>,
+    mode: BlendMode,
+    layer: &'static str,
+) -> Result<(), RenderError> {
+    let mut shader = shader.ok_or(RenderError::MissingTexture(layer))?;
+    frame.draw_bleeding(quad, bleed, &mut shader, mode);
+    Ok(())
+}
+
+/// A planet between the halves of its rings, the way its children sort.
+fn draw_planet(
+    frame: &mut Frame,
+    planet: &planets::Planet,
+    images: &Assets<Image>,
+    mode: BlendMode,
+) -> Result<(), RenderError> {
+    let halves = planet.rings.as_ref().map(|rings| rings.halves());
+    if let Some([back, _]) = &halves {
+        let shader = cpu::Rings::new(back, images);
+        draw(frame, rings::quad(back), shader, mode, "rings")?;
+    }
+    let shader = cpu::Planets::new(&planet.material, images);
+    draw(frame, planets::quad(planet), shader, mode, "planets")?;
+    if let Some(atmosphere) = &planet.atmosphere {
+        let shader = cpu::Atmosphere::new(atmosphere, images);
+        draw(
+            frame,
+            atmosphere::quad(atmosphere),
+            shader,
+            mode,
+            "atmosphere",
+        )?;
+    }
+    if let Some([_, front]) = &halves {
+        let shader = cpu::Rings::new(front, images);
+        draw(frame, rings::quad(front), shader, mode, "rings")?;
+    }
+    Ok(())
+}
+
+/// Loads a PNG the way the `AssetServer` does with default settings.
+fn load_image(path: &Path) -> Result<Image, RenderError> {
+    let bytes = fs::read(path).map_err(|source| RenderError::ReadAsset {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Default,
+        RenderAssetUsages::default(),
+    )
+    .map_err(|source| RenderError::DecodeAsset {
+        path: path.to_path_buf(),
+        source,
+    })
+}
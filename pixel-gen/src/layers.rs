@@ -0,0 +1,315 @@
+//! Layers of the scene, and the registry that toggles, lists and regenerates them.
+//!
+//! Every layer, built in or added by another crate through
+//! [`PixelSpace::add_background_layer`], is regenerated when the scene is,
+//! gets its own seed stream and a button in the demo menu, and can be toggled
+//! from the config file, the keyboard or the menu.
+
+use bevy::{
+    ecs::system::{StaticSystemParam, SystemParam, SystemParamItem},
+    render::render_resource::{
+        BlendComponent, BlendFactor, BlendOperation, BlendState, RenderPipelineDescriptor,
+    },
+};
+use serde::Deserialize;
+
+use crate::{config::ResolvedOptions, *};
+
+/// A layer of the scene. It is implemented by the marker component every
+/// entity of the layer carries, so regenerating can despawn them.
+pub trait BackgroundLayer: Component + Sized {
+    /// Names the layer's seed stream and its toggle under `[layers]` in the
+    /// config; the menu shows it in capitals.
+    const NAME: &'static str;
+    /// Toggles the layer in the demo.
+    const KEY: Option<KeyCode> = None;
+    /// Whether the layer is shown when the options don't say.
+    const ENABLED: bool = true;
+
+    /// What `spawn` needs from the world besides `Commands`.
+    type Param: SystemParam;
+
+    /// Spawns the layer's entities, each with this component. Only called
+    /// when the layer is enabled, after the previous ones are despawned.
+    fn spawn(
+        commands: &mut Commands,
+        options: &ResolvedOptions,
+        param: &mut SystemParamItem<Self::Param>,
+    );
+
+    /// Removes what `spawn` spawned.
+    fn despawn(commands: &mut Commands, entities: impl Iterator<Item = Entity>) {
+        for entity in entities {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    /// Replaces the layer with a freshly generated one, or just clears it
+    /// when it is disabled.
+    fn regenerate(
+        commands: &mut Commands,
+        entities: impl Iterator<Item = Entity>,
+        options: &ResolvedOptions,
+        param: &mut SystemParamItem<Self::Param>,
+    ) {
+        Self::despawn(commands, entities);
+        if Self::enabled(options) {
+            Self::spawn(commands, options, param);
+        }
+    }
+
+    fn enabled(options: &Options) -> bool {
+        options
+            .layers
+            .get(Self::NAME)
+            .copied()
+            .unwrap_or(Self::ENABLED)
+    }
+
+    fn set_enabled(options: &mut Options, enabled: bool) {
+        options.layers.insert(Self::NAME.to_string(), enabled);
+    }
+
+    /// The layer's own seed stream, so adding a layer never changes the others.
+    fn rng(options: &Options) -> SeedRng {
+        options.seed.stream(Self::NAME)
+    }
+}
+
+/// Sent to regenerate every layer.
+#[derive(Event)]
+pub struct RegenerateLayersEvent;
+
+/// A registered layer, as the controls and the menu see it.
+pub struct LayerEntry {
+    pub name: &'static str,
+    pub key: Option<KeyCode>,
+    /// Id of the layer's menu button.
+    pub(crate) button: &'static str,
+    pub enabled: fn(&Options) -> bool,
+    pub set_enabled: fn(&mut Options, bool),
+}
+
+impl LayerEntry {
+    /// The layer's name as the menu shows it.
+    pub fn label(&self) -> String {
+        self.name.replace('_', " ").to_uppercase()
+    }
+}
+
+/// Every registered layer, in the order they were added.
+#[derive(Resource, Default)]
+pub struct LayerRegistry {
+    layers: Vec<LayerEntry>,
+}
+
+impl LayerRegistry {
+    pub fn iter(&self) -> impl Iterator<Item = &LayerEntry> {
+        self.layers.iter()
+    }
+
+    pub(crate) fn add<L: BackgroundLayer>(&mut self) {
+        if self.layers.iter().any(|layer| layer.name == L::NAME) {
+            warn!("layer {} is already registered", L::NAME);
+            return;
+        }
+
+        self.layers.push(LayerEntry {
+            name: L::NAME,
+            key: L::KEY,
+            // element ids are static; layers are only registered while the
+            // app is built, so this leaks one short string per layer
+            button: Box::leak(format!("BTN-{}", L::NAME.replace('_', "-").to_uppercase()).into()),
+            enabled: L::enabled,
+            set_enabled: L::set_enabled,
+        });
+    }
+}
+
+pub fn setup(mut writer: EventWriter<RegenerateLayersEvent>) {
+    writer.send(RegenerateLayersEvent);
+}
+
+pub fn regenerate_layer<L: BackgroundLayer>(
+    mut events: EventReader<RegenerateLayersEvent>,
+    mut commands: Commands,
+    query: Query<Entity, With<L>>,
+    options: ResolvedOptions,
+    mut param: StaticSystemParam<L::Param>,
+) {
+    let Some(_) = events.read().next() else {
+        return;
+    };
+    events.clear();
+
+    L::regenerate(&mut commands, query.iter(), &options, &mut param);
+}
+
+/// Flips the layer whose key was just pressed; returns true if there was one.
+pub(crate) fn toggle_pressed(
+    registry: &LayerRegistry,
+    options: &mut Options,
+    kb_input: &ButtonInput<KeyCode>,
+) -> bool {
+    let Some(layer) = registry
+        .iter()
+        .find(|layer| layer.key.is_some_and(|key| kb_input.just_pressed(key)))
+    else {
+        return false;
+    };
+
+    let enabled = (layer.enabled)(options);
+    (layer.set_enabled)(options, !enabled);
+    true
+}
+
+/// Where each built in layer sits in the 2d pass, which draws lower z first.
+/// Planets step up by 0.01 each from theirs.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LayerZ {
+    pub nebulae: f32,
+    pub dust: f32,
+    pub stars: f32,
+    pub galaxies: f32,
+    pub asteroids: f32,
+    pub comets: f32,
+    pub black_hole: f32,
+    pub planets: f32,
+}
+
+impl LayerZ {
+    pub const DEFAULT: LayerZ = LayerZ {
+        nebulae: 0.,
+        dust: 0.1,
+        stars: 0.2,
+        galaxies: 0.25,
+        asteroids: 0.5,
+        comets: 0.6,
+        black_hole: 0.75,
+        planets: 1.,
+    };
+}
+
+impl Default for LayerZ {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How each built in layer is composited over the layers behind it.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BlendModes {
+    pub nebulae: BlendMode,
+    pub dust: BlendMode,
+    pub stars: BlendMode,
+    pub galaxies: BlendMode,
+    pub asteroids: BlendMode,
+    pub comets: BlendMode,
+    pub black_hole: BlendMode,
+    /// Also used for their rings and atmospheres.
+    pub planets: BlendMode,
+}
+
+impl BlendModes {
+    pub const DEFAULT: BlendModes = BlendModes {
+        nebulae: BlendMode::Alpha,
+        dust: BlendMode::Alpha,
+        stars: BlendMode::Alpha,
+        galaxies: BlendMode::Alpha,
+        asteroids: BlendMode::Alpha,
+        comets: BlendMode::Alpha,
+        black_hole: BlendMode::Alpha,
+        planets: BlendMode::Alpha,
+    };
+}
+
+impl Default for BlendModes {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Every mode weighs the layer by its alpha, so transparent pixels leave
+/// what is behind them alone.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    /// Paints over what is behind.
+    #[default]
+    Alpha,
+    /// Adds to what is behind, for glows.
+    Additive,
+    /// Lightens what is behind, without blowing it out like `Additive`.
+    Screen,
+    /// Darkens what is behind, for shadows and tints.
+    Multiply,
+}
+
+impl BlendMode {
+    /// Sets up a material's pipeline for this mode. Apart from `Alpha`, the
+    /// shaders premultiply their output under `PREMULTIPLY_ALPHA`, as the
+    /// blend factors alone can't weigh these by alpha.
+    pub(crate) fn specialize(self, descriptor: &mut RenderPipelineDescriptor) {
+        let color = |src_factor, dst_factor| BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: BlendOperation::Add,
+        };
+        let color = match self {
+            BlendMode::Alpha => BlendState::ALPHA_BLENDING.color,
+            BlendMode::Additive => color(BlendFactor::One, BlendFactor::One),
+            BlendMode::Screen => color(BlendFactor::One, BlendFactor::OneMinusSrc),
+            BlendMode::Multiply => color(BlendFactor::Dst, BlendFactor::OneMinusSrcAlpha),
+        };
+
+        let fragment = descriptor.fragment.as_mut().unwrap();
+        if self != BlendMode::Alpha {
+            fragment.shader_defs.push("PREMULTIPLY_ALPHA".into());
+        }
+        for target in fragment.targets.iter_mut().flatten() {
+            target.blend = Some(BlendState {
+                color,
+                alpha: BlendState::ALPHA_BLENDING.alpha,
+            });
+        }
+    }
+}
+
+/// Makes a material with a `blend: BlendMode` field a `Material2d` drawn by
+/// `shaders/output/<shader>.vert` and `.frag`. The blend mode is not a
+/// uniform but the material's bind group data, so each mode gets its own
+/// pipeline, set up by [`BlendMode::specialize`].
+macro_rules! blended_material {
+    ($material:ty, $shader:literal) => {
+        impl From<&$material> for $crate::BlendMode {
+            fn from(material: &$material) -> Self {
+                material.blend
+            }
+        }
+
+        impl bevy::sprite::Material2d for $material {
+            fn fragment_shader() -> bevy::render::render_resource::ShaderRef {
+                concat!("shaders/output/", $shader, ".frag").into()
+            }
+
+            fn vertex_shader() -> bevy::render::render_resource::ShaderRef {
+                concat!("shaders/output/", $shader, ".vert").into()
+            }
+
+            fn specialize(
+                descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
+                _: &bevy::render::mesh::MeshVertexBufferLayoutRef,
+                key: bevy::sprite::Material2dKey<Self>,
+            ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
+                descriptor.vertex.entry_point = "main".into();
+                descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
+                key.bind_group_data.specialize(descriptor);
+                Ok(())
+            }
+        }
+    };
+}
+
+pub(crate) use blended_material;
@@ -1,21 +1,61 @@
+mod asteroids;
+mod atmosphere;
 mod background;
+mod black_hole;
 mod colorscheme;
+mod comets;
 mod config;
 mod controls;
+pub mod cpu;
+pub mod export;
+mod galaxies;
+pub mod headless;
+mod layers;
 mod nebulae;
+mod parallax;
 mod planets;
+mod rings;
+mod seed;
 mod shaders;
 mod star_stuff;
 mod stars;
+mod tile;
 mod ui;
 
+// lets the derives name `::pixel_gen` from inside this crate too
+extern crate self as pixel_gen;
+
+use std::path::PathBuf;
+
 pub(crate) use bevy::{
     prelude::*,
-    sprite::{Material2d, Material2dPlugin, MaterialMesh2dBundle},
+    sprite::{Material2dPlugin, MaterialMesh2dBundle},
 };
 
-pub use colorscheme::ColorScheme;
-pub use config::{Options, ScreenSize};
+pub use asteroids::{AsteroidMaterial, AsteroidSettings};
+pub use atmosphere::AtmosphereMaterial;
+pub use black_hole::{BlackHoleMaterial, BlackHoleSettings};
+pub use colorscheme::{ColorScheme, ColorSchemeDir, ColorSchemes, Palette};
+pub use comets::{CometMaterial, CometSettings};
+pub use config::{ConfigError, ConfigFile, Options, ResolvedOptions, ScreenSize};
+pub use galaxies::{GalaxyMaterial, GalaxySettings};
+pub use layers::{
+    BackgroundLayer, BlendMode, BlendModes, LayerEntry, LayerRegistry, LayerZ,
+    RegenerateLayersEvent,
+};
+pub use nebulae::{NebulaeMaterial, NebulaeSettings};
+pub use parallax::{Parallax, ParallaxCamera, ParallaxSettings};
+pub use pixel_gen_derive::{AnimatedMaterial2D, AnimatedMaterialConfig};
+pub use planets::{PlanetKind, PlanetLight, PlanetSettings, PlanetWeights, PlanetsMaterial};
+pub use rings::RingMaterial;
+pub use seed::{GenerationSeed, SeedRng};
+pub use shaders::{
+    animate_material, split_track, AnimatedMaterial2D, AnimatedMaterialConfig, AnimatedUniform,
+    Animation, Easing, Keyframe, Repeat, Tracks,
+};
+pub use star_stuff::{DustSettings, StarStuffMaterial};
+pub use stars::{BigStarMaterial, StarSettings};
+pub use tile::{find_seams, Edge, Seam};
 
 #[derive(Event)]
 struct RefreshAllEvent;
@@ -28,11 +68,18 @@ pub trait PixelSpace {
     fn configure_default_pixel_gen(&mut self) -> &mut Self;
     fn configure_pixel_gen(&mut self, options: Options) -> &mut Self;
     fn configure_demo_ui(&mut self) -> &mut Self;
+    fn configure_config_file(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+    fn configure_color_scheme_dir(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+    /// Adds a layer that is regenerated, toggled and listed with the built in ones.
+    fn add_background_layer<L: BackgroundLayer>(&mut self) -> &mut Self;
 }
 
 impl PixelSpace for App {
     fn configure_default_pixel_gen(&mut self) -> &mut Self {
-        self.configure_pixel_gen(config::DEFAULT_OPTIONS)
+        self.configure_pixel_gen(Options {
+            seed: GenerationSeed::random(),
+            ..config::DEFAULT_OPTIONS
+        })
     }
 
     fn configure_demo_ui(&mut self) -> &mut Self {
@@ -41,45 +88,120 @@ impl PixelSpace for App {
             .add_systems(Update, (ui::spawn_menu, ui::refresh))
     }
 
+    fn configure_config_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.insert_resource(ConfigFile::new(path))
+            .add_systems(Startup, config::load_config_file)
+            .add_systems(Update, config::watch_config_file)
+    }
+
+    fn configure_color_scheme_dir(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.insert_resource(ColorSchemeDir(path.into()))
+    }
+
+    fn add_background_layer<L: BackgroundLayer>(&mut self) -> &mut Self {
+        self.init_resource::<LayerRegistry>()
+            .add_event::<RegenerateLayersEvent>()
+            .world_mut()
+            .resource_mut::<LayerRegistry>()
+            .add::<L>();
+        self.add_systems(Update, layers::regenerate_layer::<L>)
+    }
+
     fn configure_pixel_gen(&mut self, options: Options) -> &mut App {
+        // in the order the menu lists them
+        self.add_background_layer::<stars::Star>()
+            .add_background_layer::<star_stuff::StarStuff>()
+            .add_background_layer::<nebulae::Nebulae>()
+            .add_background_layer::<planets::Planets>()
+            .add_background_layer::<asteroids::Asteroid>()
+            .add_background_layer::<galaxies::Galaxy>()
+            .add_background_layer::<black_hole::BlackHole>()
+            .add_background_layer::<comets::Comet>();
+
         self.add_plugins((
             Material2dPlugin::<nebulae::NebulaeMaterial>::default(),
             Material2dPlugin::<star_stuff::StarStuffMaterial>::default(),
             Material2dPlugin::<planets::PlanetsMaterial>::default(),
             Material2dPlugin::<stars::BigStarMaterial>::default(),
+            Material2dPlugin::<rings::RingMaterial>::default(),
+            Material2dPlugin::<atmosphere::AtmosphereMaterial>::default(),
+            Material2dPlugin::<asteroids::AsteroidMaterial>::default(),
+            Material2dPlugin::<galaxies::GalaxyMaterial>::default(),
+            Material2dPlugin::<black_hole::BlackHoleMaterial>::default(),
+            Material2dPlugin::<comets::CometMaterial>::default(),
         ))
-        .add_event::<nebulae::SpawnNebulaeEvent>()
-        .add_event::<star_stuff::SpawnStarStuffEvent>()
-        .add_event::<planets::SpawnPlanetsEvent>()
-        .add_event::<stars::SpawnBigStarEvent>()
+        .init_resource::<comets::Comets>()
+        .init_resource::<parallax::Parallax>()
         .add_event::<background::SpawnBackgroundEvent>()
         .add_event::<RefreshAllEvent>()
         .insert_resource(options)
-        .add_systems(Startup, (spawn_camera, background::setup))
+        .init_resource::<ColorSchemes>()
+        .init_resource::<ColorSchemeDir>()
         .add_systems(
-            PostStartup,
-            (nebulae::setup, star_stuff::setup, planets::setup),
+            Startup,
+            (
+                spawn_camera,
+                background::setup,
+                colorscheme::load_color_schemes,
+            ),
         )
+        .add_systems(PostStartup, layers::setup)
         .add_systems(FixedUpdate, config::update_screen_size)
         .add_systems(
             Update,
             (
-                nebulae::spawn_nebulae,
-                planets::spawn_planets,
-                star_stuff::spawn_star_stuff,
                 controls::controls,
                 config::change_options,
                 utils::common_systems::exit_on_q,
                 background::spawn,
                 (planets::update_scale, planets::lerp_scale).chain(),
-                stars::spawn_star,
+                (comets::spawn_comets, comets::move_comets).chain(),
                 shaders::animate_material::<nebulae::NebulaeMaterial, nebulae::NebulaeConfig>,
                 shaders::animate_material::<
                     star_stuff::StarStuffMaterial,
                     star_stuff::StarStuffConfig,
                 >,
                 shaders::animate_material::<planets::PlanetsMaterial, planets::PlanetsConfig>,
+                shaders::animate_material::<
+                    atmosphere::AtmosphereMaterial,
+                    atmosphere::AtmosphereConfig,
+                >,
+                shaders::animate_material::<stars::BigStarMaterial, stars::StarConfig>,
+                shaders::animate_material::<
+                    black_hole::BlackHoleMaterial,
+                    black_hole::BlackHoleConfig,
+                >,
             ),
         )
+        .add_systems(
+            PostUpdate,
+            (
+                shaders::scale_material::<planets::PlanetsMaterial>,
+                shaders::scale_material::<rings::RingMaterial>,
+                shaders::scale_material::<atmosphere::AtmosphereMaterial>,
+            )
+                .after(TransformSystem::TransformPropagate),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                parallax::update_parallax,
+                (
+                    background::follow_camera,
+                    parallax::scroll_material::<nebulae::NebulaeMaterial>,
+                    parallax::scroll_material::<star_stuff::StarStuffMaterial>,
+                    parallax::scroll_material::<galaxies::GalaxyMaterial>,
+                    parallax::scroll_material::<stars::BigStarMaterial>,
+                    parallax::scroll_material::<asteroids::AsteroidMaterial>,
+                    parallax::scroll_material::<black_hole::BlackHoleMaterial>,
+                    parallax::scroll_material::<planets::PlanetsMaterial>,
+                    parallax::scroll_material::<rings::RingMaterial>,
+                    parallax::scroll_material::<atmosphere::AtmosphereMaterial>,
+                ),
+            )
+                .chain()
+                // follows the camera once it has moved this frame
+                .after(TransformSystem::TransformPropagate),
+        )
     }
 }
@@ -1,7 +1,23 @@
 use bevy::{prelude::*, window::WindowMode};
 use pixel_gen::*;
 
+mod cli;
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("render") => Some(cli::render(&args[1..])),
+        Some("export") => Some(cli::export(&args[1..])),
+        _ => None,
+    };
+    if let Some(result) = result {
+        if let Err(err) = result {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -11,6 +27,7 @@ fn main() {
             ..default()
         }))
         .configure_default_pixel_gen()
+        .configure_config_file("config.toml")
         .configure_demo_ui()
         .run();
 }
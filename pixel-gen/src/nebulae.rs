@@ -1,196 +1,232 @@
-use bevy::{
-    reflect::TypePath,
-    render::render_resource::{AsBindGroup, ShaderRef},
-};
+use std::ops::Range;
 
-use rand::Rng;
-use shaders::{AnimatedMaterial2D, AnimatedMaterialConfig};
+use bevy::{ecs::system::SystemParam, reflect::TypePath, render::render_resource::AsBindGroup};
 
-use crate::*;
+use rand::Rng;
+use serde::Deserialize;
+use shaders::{cycle, Animation, Easing, Repeat, Tracks};
 
-#[derive(Event)]
-pub struct SpawnNebulaeEvent;
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    *,
+};
 
 #[derive(Component)]
 pub struct Nebulae;
 
-pub fn setup(mut event_writer: EventWriter<SpawnNebulaeEvent>) {
-    event_writer.send(SpawnNebulaeEvent);
-}
-
-pub fn spawn_nebulae(
-    mut trigger: EventReader<SpawnNebulaeEvent>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<NebulaeMaterial>>,
-    options: Res<config::Options>,
-    current_nebulae: Query<Entity, With<Nebulae>>,
-    mut asset_server: ResMut<Assets<Image>>,
-) {
-    let Some(_) = trigger.read().next() else {
-        return;
-    };
-    trigger.clear();
+/// Seconds the noise takes to zoom in, before zooming back out, with `animate`.
+const ZOOM_SECONDS: f32 = 60.;
+/// Seconds the seed takes to drift away, before drifting back.
+const DRIFT_SECONDS: f32 = 90.;
 
-    if let Ok(entity) = current_nebulae.get_single() {
-        commands.entity(entity).despawn_recursive();
-    }
+#[derive(SystemParam)]
+pub struct NebulaeSpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<NebulaeMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+}
 
-    if !options.nebulae {
-        return;
+impl BackgroundLayer for Nebulae {
+    const NAME: &'static str = "nebulae";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyN);
+
+    type Param = NebulaeSpawner<'static>;
+
+    fn spawn(commands: &mut Commands, options: &config::ResolvedOptions, sn: &mut NebulaeSpawner) {
+        let mat = generate(options, options.palette(), &mut sn.images);
+
+        let drift = |start: f32, end: f32, seconds: f32| {
+            Animation::between(start, end, seconds, Repeat::PingPong, Easing::SineInOut)
+        };
+        let animation_config = NebulaeConfig::new(
+            Tracks::new()
+                .with("size", drift(mat.size, mat.size + 1., ZOOM_SECONDS))
+                // the clouds slowly reshape as they flow
+                .with("seed", drift(mat.seed, mat.seed + 0.1, DRIFT_SECONDS))
+                .with("time", cycle(options.nebulae_settings.flow_period)),
+        );
+        let layer = ParallaxLayer::new(
+            options.parallax_settings.nebulae,
+            mat.x_offset,
+            options.screen_size.rect(),
+        );
+
+        commands.spawn((
+            Nebulae,
+            animation_config,
+            layer,
+            MaterialMesh2dBundle {
+                mesh: sn
+                    .meshes
+                    .add(Rectangle::from_size(quad(options).size()))
+                    .into(),
+                material: sn.materials.add(mat),
+                transform: Transform::from_xyz(0., 0., options.layer_z.nebulae),
+                ..default()
+            },
+        ));
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.nebulae
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.nebulae = enabled;
     }
+}
 
-    let mat = NebulaeMaterial::new(
-        &options,
-        &mut asset_server,
+/// The nebulae material for `options`, drawn from the "nebulae" seed stream.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> NebulaeMaterial {
+    NebulaeMaterial::new(
+        options,
+        palette,
+        images,
+        &mut options.seed.stream("nebulae"),
         options.screen_size.x_offset(),
         &options.screen_size,
-    );
-
-    let mut animation_config = NebulaeConfig::new();
-
-    animation_config.start(mat.get(), mat.get() + 1.);
-
-    commands.spawn((
-        Nebulae,
-        animation_config,
-        MaterialMesh2dBundle {
-            mesh: meshes
-                .add(Rectangle::from_size(Vec2::new(
-                    options.screen_size.width(),
-                    options.screen_size.height(),
-                )))
-                .into(),
-            material: materials.add(mat),
-            ..default()
-        },
-    ));
+    )
 }
 
-#[derive(Component)]
-pub struct NebulaeConfig {
-    default: shaders::DefaultAnimationConfig,
+/// World space covered by the nebulae mesh once the vertex shader offsets it.
+pub(crate) fn quad(options: &config::Options) -> Rect {
+    Rect::from_center_size(
+        Vec2::new(options.screen_size.x_offset(), 0.),
+        Vec2::new(options.screen_size.width(), options.screen_size.height()),
+    )
 }
 
-impl NebulaeConfig {
-    pub fn new() -> Self {
-        NebulaeConfig {
-            default: shaders::DefaultAnimationConfig::default(),
-        }
-    }
+/// Ranges the nebulae's noise is drawn from.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NebulaeSettings {
+    /// Octaves of fbm noise; more is more detailed.
+    pub octaves: Range<i32>,
+    pub seed: Range<f32>,
+    /// How far the clouds are pushed around as they flow, in noise cells;
+    /// 0 leaves them undistorted.
+    pub flow: f32,
+    /// Seconds the flow takes to come back round to where it started, with
+    /// `animate`.
+    pub flow_period: f32,
 }
 
-// TODO: This could be a derive macro
-impl shaders::AnimatedMaterialConfig for NebulaeConfig {
-    fn start(&mut self, start: f32, target: f32) {
-        self.default.start(start, target);
-    }
-
-    fn progress(&self) -> f32 {
-        self.default.progress()
-    }
-
-    fn update_progress(&mut self, new_progress: f32) {
-        self.default.update_progress(new_progress);
-    }
-
-    fn target(&self) -> f32 {
-        self.default.target()
-    }
+impl NebulaeSettings {
+    pub const DEFAULT: NebulaeSettings = NebulaeSettings {
+        octaves: 3..5,
+        seed: 1.0..50.0,
+        flow: 0.5,
+        flow_period: 60.0,
+    };
+}
 
-    fn change_direction(&mut self) {
-        self.default.change_direction();
+impl Default for NebulaeSettings {
+    fn default() -> Self {
+        Self::DEFAULT
     }
+}
 
-    fn cycle(&self) -> bool {
-        self.default.cycle()
-    }
+#[derive(Component, AnimatedMaterialConfig)]
+pub struct NebulaeConfig {
+    #[animation]
+    tracks: Tracks,
+}
 
-    fn speed(&self) -> f32 {
-        self.default.speed()
+impl NebulaeConfig {
+    pub fn new(tracks: Tracks) -> Self {
+        NebulaeConfig { tracks }
     }
 }
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
 pub struct NebulaeMaterial {
     #[uniform(0)]
-    size: f32,
+    #[animate]
+    pub(crate) size: f32,
     #[uniform(3)]
-    octaves: i32,
+    pub(crate) octaves: i32,
     #[uniform(4)]
-    seed: f32,
+    #[animate]
+    pub(crate) seed: f32,
     #[uniform(5)]
-    pixels: f32,
+    pub(crate) pixels: f32,
     #[uniform(6)]
-    background_color: Vec4,
+    pub(crate) background_color: Vec4,
     #[uniform(7)]
-    uv_correct: Vec2,
+    pub(crate) uv_correct: Vec2,
     #[uniform(8)]
-    should_tile: i32,
+    pub(crate) should_tile: i32,
     #[uniform(9)]
-    reduce_background: i32,
+    pub(crate) reduce_background: i32,
     #[uniform(10)]
-    x_offset: Vec3,
+    pub(crate) x_offset: Vec3,
+    /// How far through the flow's loop the clouds are, from 0 to 1.
     #[uniform(11)]
-    time: f32,
+    #[animate]
+    pub(crate) time: f32,
+    #[uniform(12)]
+    pub(crate) transparent: i32,
+    /// Offset of the texture in parallax mode, in uv.
+    #[uniform(13)]
+    pub(crate) scroll: Vec2,
+    #[uniform(14)]
+    pub(crate) flow: f32,
 
     #[texture(1)]
     #[sampler(2)]
-    color_texture: Option<Handle<Image>>,
-}
-
-impl shaders::AnimatedMaterial2D for NebulaeMaterial {
-    fn get(&self) -> f32 {
-        self.size
-    }
+    pub(crate) color_texture: Option<Handle<Image>>,
 
-    fn update(&mut self, new_value: f32) {
-        self.size = new_value
-    }
+    pub(crate) blend: BlendMode,
 }
 
 impl NebulaeMaterial {
     fn new(
         options: &config::Options,
+        palette: &Palette,
         asset_server: &mut Assets<Image>,
+        rng: &mut impl Rng,
         x_offset: f32,
         screen_size: &config::ScreenSize,
     ) -> Self {
-        let (image, bg) = options.colorscheme.gradient_image_with_bg();
-        let mut rng = rand::thread_rng();
+        let (image, bg) = palette.gradient_image_with_bg();
+        let should_tile = options.tile || options.parallax;
+        let (size, pixels) = (screen_size.width() / options.pixels, options.pixels);
+        let (size, pixels) = if should_tile {
+            tile::snap_noise(size, pixels)
+        } else {
+            (size, pixels)
+        };
 
         NebulaeMaterial {
-            size: (screen_size.width() / options.pixels),
-            octaves: rng.gen_range(3..5),
-            seed: rng.gen_range(1.0..50.0),
-            pixels: options.pixels,
+            size,
+            octaves: config::sample(rng, &options.nebulae_settings.octaves),
+            seed: config::sample(rng, &options.nebulae_settings.seed),
+            pixels,
             background_color: bg.to_srgba().to_vec4(),
             uv_correct: options.screen_size.aspect(),
             color_texture: Some(asset_server.add(image)),
-            should_tile: options.tile as i32,
+            should_tile: should_tile as i32,
             time: 0.,
             reduce_background: options.darken as i32,
+            transparent: options.transparency as i32,
             x_offset: Vec3::new(x_offset, 0., 0.),
+            scroll: Vec2::ZERO,
+            flow: options.nebulae_settings.flow,
+            blend: options.blend_modes.nebulae,
         }
     }
 }
 
-impl Material2d for NebulaeMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/output/nebulae.frag".into()
-    }
-
-    fn vertex_shader() -> ShaderRef {
-        "shaders/output/nebulae.vert".into()
-    }
-
-    fn specialize(
-        descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
-        _: &bevy::render::mesh::MeshVertexBufferLayoutRef,
-        _: bevy::sprite::Material2dKey<Self>,
-    ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        descriptor.vertex.entry_point = "main".into();
-        descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
-        Ok(())
+impl ParallaxMaterial2D for NebulaeMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.x_offset = layer.origin() + anchor.extend(0.);
+        self.scroll = (offset / layer.scene().size() * Vec2::new(1., -1.)).rem_euclid(Vec2::ONE);
     }
 }
+
+layers::blended_material!(NebulaeMaterial, "nebulae");
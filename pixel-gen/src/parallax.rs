@@ -0,0 +1,171 @@
+use bevy::{prelude::*, sprite::Material2d};
+use serde::Deserialize;
+
+use crate::config::Options;
+
+/// How far the scene has scrolled, for parallax mode.
+///
+/// Games either move a camera marked [`ParallaxCamera`], which the scene then
+/// stays centred on, or set `velocity` to drift it.
+#[derive(Resource, Default)]
+pub struct Parallax {
+    /// Screen pixels per second; overrides `ParallaxSettings::velocity`.
+    pub velocity: Option<Vec2>,
+    travelled: Vec2,
+    anchor: Vec2,
+}
+
+impl Parallax {
+    /// How far the nearest layers have scrolled, in screen pixels.
+    pub fn offset(&self) -> Vec2 {
+        self.anchor + self.travelled
+    }
+
+    /// Where the scene is centred in the world, following the camera.
+    pub fn anchor(&self) -> Vec2 {
+        self.anchor
+    }
+}
+
+/// The camera parallax mode follows; at most one.
+#[derive(Component)]
+pub struct ParallaxCamera;
+
+/// Depth of each layer: how far it scrolls relative to the camera, from 0
+/// (fixed to the screen) to 1 (moving with the world).
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ParallaxSettings {
+    pub nebulae: f32,
+    pub dust: f32,
+    pub galaxies: f32,
+    pub stars: f32,
+    pub asteroids: f32,
+    pub black_hole: f32,
+    pub planets: f32,
+    /// Comets and shooting stars, which keep the scroll they appeared at.
+    pub comets: f32,
+    /// Drift in screen pixels per second when nothing else moves the scene.
+    pub velocity: [f32; 2],
+}
+
+impl ParallaxSettings {
+    pub const DEFAULT: ParallaxSettings = ParallaxSettings {
+        nebulae: 0.1,
+        dust: 0.2,
+        galaxies: 0.05,
+        stars: 0.35,
+        asteroids: 0.8,
+        black_hole: 0.5,
+        planets: 0.6,
+        comets: 0.7,
+        velocity: [20., 0.],
+    };
+}
+
+impl Default for ParallaxSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// A layer entity that scrolls with [`Parallax`].
+#[derive(Component)]
+pub struct ParallaxLayer {
+    pub depth: f32,
+    /// Where the material placed the layer when it was spawned.
+    origin: Vec3,
+    /// The point wrapped around the scene, shared by a planet and its rings so
+    /// they wrap together.
+    pivot: Vec2,
+    /// How far past the edge of the scene `pivot` goes before it wraps.
+    margin: f32,
+    /// The scene the layer was spawned into.
+    scene: Rect,
+}
+
+impl ParallaxLayer {
+    pub(crate) fn new(depth: f32, origin: Vec3, scene: Rect) -> Self {
+        ParallaxLayer {
+            depth,
+            origin,
+            pivot: origin.truncate(),
+            margin: 0.,
+            scene,
+        }
+    }
+
+    /// Wraps around `pivot` once it is `margin` past the edge of the scene.
+    pub(crate) fn wrapping(mut self, pivot: Vec2, margin: f32) -> Self {
+        self.pivot = pivot;
+        self.margin = margin;
+        self
+    }
+
+    pub fn origin(&self) -> Vec3 {
+        self.origin
+    }
+
+    pub fn scene(&self) -> Rect {
+        self.scene
+    }
+
+    /// How far the layer has moved from its origin after scrolling `offset`,
+    /// with everything that leaves one side of the scene entering the other.
+    pub fn wrap(&self, offset: Vec2) -> Vec2 {
+        let area = self.scene.inflate(self.margin);
+        let pivot = self.pivot - offset;
+        let wrapped = area.min + (pivot - area.min).rem_euclid(area.size());
+        wrapped - self.pivot
+    }
+
+    /// Where a material placed by a `position` uniform goes after scrolling.
+    pub fn position(&self, anchor: Vec2, offset: Vec2) -> Vec3 {
+        self.origin + (anchor + self.wrap(offset)).extend(0.)
+    }
+}
+
+/// Materials that move themselves when their layer scrolls.
+pub trait ParallaxMaterial2D: Material2d {
+    /// `anchor` is where the scene is centred in the world, `offset` how far
+    /// this layer has scrolled in screen pixels, already scaled by its depth.
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2);
+}
+
+pub fn update_parallax(
+    time: Res<Time>,
+    options: Res<Options>,
+    mut parallax: ResMut<Parallax>,
+    camera: Query<&GlobalTransform, With<ParallaxCamera>>,
+) {
+    if !options.parallax {
+        return;
+    }
+
+    let velocity = parallax
+        .velocity
+        .unwrap_or(Vec2::from(options.parallax_settings.velocity));
+    parallax.travelled += velocity * time.delta_seconds();
+    parallax.anchor = camera
+        .get_single()
+        .map_or(Vec2::ZERO, |transform| transform.translation().truncate());
+}
+
+pub fn scroll_material<M: ParallaxMaterial2D>(
+    options: Res<Options>,
+    parallax: Res<Parallax>,
+    query: Query<(&Handle<M>, &ParallaxLayer)>,
+    mut material_assets: ResMut<Assets<M>>,
+) {
+    if !options.parallax {
+        return;
+    }
+
+    for (handle, layer) in query.iter() {
+        let Some(material) = material_assets.get_mut(handle.id()) else {
+            continue;
+        };
+
+        material.scroll(layer, parallax.anchor, parallax.offset() * layer.depth);
+    }
+}
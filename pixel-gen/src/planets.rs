@@ -1,11 +1,16 @@
-use bevy::render::render_resource::{AsBindGroup, ShaderRef};
-use rand::Rng;
-use shaders::{AnimatedMaterial2D, AnimatedMaterialConfig, DefaultAnimationConfig};
+use std::ops::Range;
 
-use crate::*;
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+use shaders::{cycle, ScaledMaterial2D, Tracks};
 
-#[derive(Event)]
-pub struct SpawnPlanetsEvent;
+use crate::{
+    atmosphere::AtmosphereMaterial,
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    rings::RingMaterial,
+    *,
+};
 
 #[derive(Component)]
 pub struct Planets {
@@ -25,177 +30,491 @@ pub fn update_scale(
         return;
     };
 
-    let Ok(mut planets) = query.get_single_mut() else {
-        return;
-    };
-
-    planets.scale += delta * time.delta_seconds();
+    for mut planets in query.iter_mut() {
+        planets.scale += delta * time.delta_seconds();
+    }
 }
 
 pub fn lerp_scale(time: Res<Time>, mut query: Query<(&mut Transform, &Planets)>) {
-    let Ok((mut transform, planets)) = query.get_single_mut() else {
-        return;
-    };
-
     const SPEED: f32 = 1.;
 
-    transform.scale = transform
-        .scale
-        .lerp(Vec3::splat(planets.scale), time.delta_seconds() * SPEED)
+    for (mut transform, planets) in query.iter_mut() {
+        transform.scale = transform
+            .scale
+            .lerp(Vec3::splat(planets.scale), time.delta_seconds() * SPEED)
+    }
+}
+
+#[derive(SystemParam)]
+pub struct PlanetSpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    planets: ResMut<'w, Assets<PlanetsMaterial>>,
+    rings: ResMut<'w, Assets<RingMaterial>>,
+    atmospheres: ResMut<'w, Assets<AtmosphereMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
 }
 
-pub fn setup(mut event_writer: EventWriter<SpawnPlanetsEvent>) {
-    for _ in 0..rand::thread_rng().gen_range(1..5) {
-        event_writer.send(SpawnPlanetsEvent);
+// Draw order within a planet, relative to its body; the 2d pass sorts by z.
+const RING_BACK_Z: f32 = -0.003;
+const ATMOSPHERE_Z: f32 = 0.001;
+const RING_FRONT_Z: f32 = 0.002;
+
+/// Seconds the light takes to circle the planet once, with `animate`.
+pub(crate) const ORBIT_SECONDS: f32 = 60.;
+/// UV across the whole way round a planet; the visible face is half of it.
+/// Matches `TURN` in the shader.
+pub(crate) const TURN: f32 = 2.;
+/// How far from a planet's centre its light sits when it comes from a star, in
+/// its spherified uv.
+const STARLIGHT_REACH: f32 = 0.4;
+
+impl BackgroundLayer for Planets {
+    const NAME: &'static str = "planets";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyP);
+
+    type Param = PlanetSpawner<'static>;
+
+    fn spawn(commands: &mut Commands, options: &config::ResolvedOptions, ps: &mut PlanetSpawner) {
+        // only light from nowhere in particular is free to circle the planets
+        let orbiting = options.planet_settings.light == PlanetLight::Random;
+        for (i, planet) in generate(options, options.palette(), &mut ps.images)
+            .into_iter()
+            .enumerate()
+        {
+            let mut tracks = Tracks::new().with("time", cycle(planet.rotation_period));
+            if orbiting {
+                tracks = tracks.with_orbit(
+                    "light_origin",
+                    Vec2::splat(0.5),
+                    planet.material.light_origin,
+                    ORBIT_SECONDS,
+                );
+            }
+            let config = PlanetsConfig::new(tracks);
+
+            // the planet and everything around it wrap as one
+            let centre = planet.material.position.truncate();
+            let margin = [
+                Some(planet.radius),
+                planet.rings.as_ref().map(|rings| rings.radius),
+                planet
+                    .atmosphere
+                    .as_ref()
+                    .map(|atmosphere| atmosphere.radius),
+            ]
+            .into_iter()
+            .flatten()
+            .fold(0., f32::max);
+            let depth = options.parallax_settings.planets;
+            let scene = options.screen_size.rect();
+            let layer = move |origin: Vec3| {
+                ParallaxLayer::new(depth, origin, scene).wrapping(centre, margin)
+            };
+
+            let rings = planet.rings.map(|rings| {
+                let mesh = ps.meshes.add(Rectangle::from_length(rings.radius * 2.));
+                rings
+                    .halves()
+                    .map(|half| (mesh.clone(), layer(half.position), ps.rings.add(half)))
+            });
+            let atmosphere = planet.atmosphere.map(|atmosphere| {
+                let mesh = ps
+                    .meshes
+                    .add(Rectangle::from_length(atmosphere.radius * 2.));
+                (
+                    mesh,
+                    layer(atmosphere.position),
+                    orbiting.then(|| atmosphere::AtmosphereConfig::orbiting(&atmosphere)),
+                    ps.atmospheres.add(atmosphere),
+                )
+            });
+
+            commands
+                .spawn((
+                    Planets { scale: 1. },
+                    config,
+                    layer(planet.material.position),
+                    MaterialMesh2dBundle {
+                        mesh: ps.meshes.add(Circle::new(planet.radius)).into(),
+                        material: ps.planets.add(planet.material),
+                        // each planet gets its own z so its rings sort with it
+                        transform: Transform::from_translation(
+                            Vec3::ZERO.with_z(options.layer_z.planets + i as f32 * 0.01),
+                        ),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    if let Some([(mesh, back_layer, back), (_, front_layer, front)]) = rings {
+                        for (material, layer, z) in [
+                            (back, back_layer, RING_BACK_Z),
+                            (front, front_layer, RING_FRONT_Z),
+                        ] {
+                            parent.spawn((
+                                rings::Ring,
+                                layer,
+                                MaterialMesh2dBundle {
+                                    mesh: mesh.clone().into(),
+                                    material,
+                                    transform: Transform::from_xyz(0., 0., z),
+                                    ..default()
+                                },
+                            ));
+                        }
+                    }
+                    if let Some((mesh, layer, config, material)) = atmosphere {
+                        let mut atmosphere = parent.spawn((
+                            atmosphere::Atmosphere,
+                            layer,
+                            MaterialMesh2dBundle {
+                                mesh: mesh.into(),
+                                material,
+                                transform: Transform::from_xyz(0., 0., ATMOSPHERE_Z),
+                                ..default()
+                            },
+                        ));
+                        if let Some(config) = config {
+                            atmosphere.insert(config);
+                        }
+                    }
+                });
+        }
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.planets
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.planets = enabled;
     }
 }
 
-pub fn spawn_planets(
-    mut trigger: EventReader<SpawnPlanetsEvent>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<PlanetsMaterial>>,
-    options: Res<config::Options>,
-    current_planet: Query<Entity, With<Planets>>,
-    mut images: ResMut<Assets<Image>>,
-) {
-    if trigger.is_empty() {
-        return;
+/// A generated planet, with whatever surrounds it.
+#[derive(Clone)]
+pub(crate) struct Planet {
+    pub(crate) material: PlanetsMaterial,
+    pub(crate) radius: f32,
+    /// Seconds it takes to turn once, with `animate`.
+    pub(crate) rotation_period: f32,
+    pub(crate) rings: Option<RingMaterial>,
+    pub(crate) atmosphere: Option<AtmosphereMaterial>,
+}
+
+impl Planet {
+    /// World space covered by the planet, its rings and its atmosphere.
+    fn bounds(&self) -> Rect {
+        let rings = self.rings.as_ref().map(rings::quad);
+        let atmosphere = self.atmosphere.as_ref().map(atmosphere::quad);
+        [rings, atmosphere]
+            .into_iter()
+            .flatten()
+            .fold(quad(self), |bounds, other| bounds.union(other))
     }
 
-    for entity in current_planet.iter() {
-        commands.entity(entity).despawn_recursive();
+    /// Moves the planet along with its rings and atmosphere.
+    fn offset(&mut self, shift: Vec2) {
+        self.material.position += shift.extend(0.);
+        if let Some(rings) = &mut self.rings {
+            rings.position += shift.extend(0.);
+        }
+        if let Some(atmosphere) = &mut self.atmosphere {
+            atmosphere.position += shift.extend(0.);
+        }
     }
+}
 
-    if !options.planets {
-        return;
+/// Every planet for `options`, drawn from the "planets" seed stream. Rings and
+/// atmospheres come from their own "rings" stream, and tilt and spin from
+/// "planet_spin", so they never move planets.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> Vec<Planet> {
+    let mut rng = options.seed.stream("planets");
+    let mut extras = options.seed.stream("rings");
+    let mut spin = options.seed.stream("planet_spin");
+
+    let settings = &options.planet_settings;
+    let kinds = settings.weights.distribution();
+    let suns = match settings.light {
+        PlanetLight::Random => Vec::new(),
+        PlanetLight::NearestStar if options.stars => stars::positions(options),
+        PlanetLight::NearestStar => Vec::new(),
+        PlanetLight::Sun([x, y]) => vec![options.screen_size.rect().center() + Vec2::new(x, y)],
+    };
+
+    let planets = (0..config::sample(&mut rng, &settings.count))
+        .map(|_| {
+            let kind = kinds.as_ref().map_or(PlanetKind::Dry, |kinds| {
+                PlanetKind::ALL[kinds.sample(&mut rng)]
+            });
+            let mut material = PlanetsMaterial::new(options, palette, images, &mut rng, kind);
+            let radius = config::sample(&mut rng, &settings.radius);
+            material.tilt = config::sample(&mut spin, &settings.axial_tilt).to_radians();
+            let rotation_period = config::sample(&mut spin, &settings.rotation_period);
+            if let Some(light_origin) = light_from(&suns, material.position.truncate()) {
+                material.light_origin = light_origin;
+            }
+            Planet {
+                rings: rings::generate(options, palette, images, &mut extras, &material, radius),
+                atmosphere: atmosphere::generate(
+                    options,
+                    palette,
+                    images,
+                    &mut extras,
+                    &material,
+                    radius,
+                ),
+                material,
+                radius,
+                rotation_period,
+            }
+        })
+        .collect();
+
+    tile::wrapped(options, planets, Planet::bounds, Planet::offset)
+}
+
+/// Where the light for a planet at `centre` comes from, when it comes from the
+/// nearest of `suns`: the same side of every planet faces each sun.
+fn light_from(suns: &[Vec2], centre: Vec2) -> Option<Vec2> {
+    let sun = suns.iter().min_by(|a, b| {
+        a.distance_squared(centre)
+            .total_cmp(&b.distance_squared(centre))
+    })?;
+    // uv runs down the screen
+    let towards = (*sun - centre).normalize_or_zero() * Vec2::new(1., -1.);
+    Some(Vec2::splat(0.5) + towards * STARLIGHT_REACH)
+}
+
+/// Where planets are lit from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanetLight {
+    /// Each planet from its own random side, circling it with `animate`.
+    #[default]
+    Random,
+    /// The nearest big star, or a random side without any.
+    NearestStar,
+    /// A sun at this point, in screen pixels from the centre of the scene with
+    /// y up, e.g. `light = { sun = [-400.0, 300.0] }`.
+    Sun([f32; 2]),
+}
+
+/// The look of a planet, after the planets of PixelPlanets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanetKind {
+    /// Cratered, moon-like rock.
+    Dry = 0,
+    /// Land masses, oceans and polar caps.
+    Terran = 1,
+    /// Frosted ice with dark cracks.
+    Ice = 2,
+    /// Dark crust with glowing rivers.
+    Lava = 3,
+    /// Turbulent bands.
+    GasGiant = 4,
+}
+
+impl PlanetKind {
+    pub const ALL: [PlanetKind; 5] = [
+        PlanetKind::Dry,
+        PlanetKind::Terran,
+        PlanetKind::Ice,
+        PlanetKind::Lava,
+        PlanetKind::GasGiant,
+    ];
+}
+
+/// How often each [`PlanetKind`] is picked, relative to the others. A kind
+/// with weight 0 never appears.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PlanetWeights {
+    pub dry: f32,
+    pub terran: f32,
+    pub ice: f32,
+    pub lava: f32,
+    pub gas_giant: f32,
+}
+
+impl PlanetWeights {
+    pub const DEFAULT: PlanetWeights = PlanetWeights {
+        dry: 1.0,
+        terran: 1.0,
+        ice: 1.0,
+        lava: 1.0,
+        gas_giant: 1.0,
+    };
+
+    /// Indexes into [`PlanetKind::ALL`], or `None` when no weight is positive.
+    fn distribution(&self) -> Option<WeightedIndex<f32>> {
+        match WeightedIndex::new([self.dry, self.terran, self.ice, self.lava, self.gas_giant]) {
+            Ok(distribution) => Some(distribution),
+            Err(err) => {
+                warn!("invalid planet weights, only generating dry planets: {err}");
+                None
+            }
+        }
     }
+}
+
+impl Default for PlanetWeights {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How many planets are generated and how they look.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PlanetSettings {
+    /// How many planets there are, drawn from this range.
+    pub count: Range<u32>,
+    /// Radius in screen pixels.
+    pub radius: Range<f32>,
+    /// Scale of the surface noise; larger is busier.
+    pub size: f32,
+    /// Pixels across the planet's diameter.
+    pub pixels: f32,
+    pub seed: Range<f32>,
+    pub weights: PlanetWeights,
+    /// Chance, from 0 to 1, that a planet has rings.
+    pub rings: f32,
+    /// Width of the rings, in planet radii.
+    pub ring_width: Range<f32>,
+    /// Tilt of the rings in degrees; 0 is level.
+    pub ring_tilt: Range<f32>,
+    /// How open the rings look, from nearly edge on towards 1, face on.
+    pub ring_inclination: Range<f32>,
+    /// Chance, from 0 to 1, that a planet has an atmosphere.
+    pub atmosphere: f32,
+    /// Seconds a planet takes to turn once, with `animate`.
+    pub rotation_period: Range<f32>,
+    /// Tilt of a planet's axis in degrees; 0 is upright.
+    pub axial_tilt: Range<f32>,
+    pub light: PlanetLight,
+}
+
+impl PlanetSettings {
+    pub const DEFAULT: PlanetSettings = PlanetSettings {
+        count: 1..5,
+        radius: 40.0..70.0,
+        size: 5.365,
+        pixels: 100.0,
+        seed: 1.0..10.0,
+        weights: PlanetWeights::DEFAULT,
+        rings: 0.3,
+        ring_width: 0.3..0.8,
+        ring_tilt: -30.0..30.0,
+        ring_inclination: 0.15..0.4,
+        atmosphere: 0.5,
+        rotation_period: 90.0..240.0,
+        axial_tilt: -25.0..25.0,
+        light: PlanetLight::Random,
+    };
+}
 
-    for _ in trigger.read() {
-        let mat = PlanetsMaterial::new(&options, &mut images);
-
-        let mut config = PlanetsConfig::new();
-
-        config.start(mat.get(), mat.get() + 10.);
-
-        commands.spawn((
-            Planets { scale: 1. },
-            config,
-            MaterialMesh2dBundle {
-                mesh: meshes
-                    .add(Circle::new(rand::thread_rng().gen_range(40.0..70.0)))
-                    .into(),
-                material: materials.add(mat),
-                transform: Transform::from_translation(Vec3::ZERO.with_z(1.0)),
-                ..default()
-            },
-        ));
+impl Default for PlanetSettings {
+    fn default() -> Self {
+        Self::DEFAULT
     }
 }
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+/// World space covered by a planet's circle mesh once the vertex shader offsets it.
+pub(crate) fn quad(planet: &Planet) -> Rect {
+    Rect::from_center_size(
+        planet.material.position.truncate(),
+        Vec2::splat(planet.radius * 2.),
+    )
+}
+
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
 pub struct PlanetsMaterial {
     #[uniform(0)]
-    size: f32,
+    #[animate]
+    pub(crate) size: f32,
     #[uniform(1)]
-    octaves: i32,
+    pub(crate) octaves: i32,
     #[uniform(2)]
-    seed: f32,
+    pub(crate) seed: f32,
     #[uniform(3)]
-    pixels: f32,
+    pub(crate) pixels: f32,
     #[uniform(4)]
-    light_origin: Vec2,
+    #[animate]
+    pub(crate) light_origin: Vec2,
 
     #[texture(5)]
     #[sampler(6)]
-    color_texture: Option<Handle<Image>>,
+    pub(crate) color_texture: Option<Handle<Image>>,
     #[uniform(7)]
-    position: Vec3,
-}
-
-impl AnimatedMaterial2D for PlanetsMaterial {
-    fn get(&self) -> f32 {
-        self.size
-    }
-
-    fn update(&mut self, new_value: f32) {
-        self.size = new_value
-    }
+    pub(crate) position: Vec3,
+    #[uniform(8)]
+    pub(crate) kind: i32,
+    #[uniform(9)]
+    pub(crate) scale: f32,
+    /// Axial tilt, in radians.
+    #[uniform(10)]
+    pub(crate) tilt: f32,
+    /// How far the planet has turned, from 0 to 1.
+    #[uniform(11)]
+    #[animate]
+    pub(crate) time: f32,
+
+    pub(crate) blend: BlendMode,
 }
 
 impl PlanetsMaterial {
-    fn new(options: &config::Options, asset_server: &mut Assets<Image>) -> Self {
+    fn new(
+        options: &config::Options,
+        palette: &Palette,
+        asset_server: &mut Assets<Image>,
+        rng: &mut impl Rng,
+        kind: PlanetKind,
+    ) -> Self {
         PlanetsMaterial {
-            size: 5.365,
+            size: options.planet_settings.size,
             octaves: 3,
-            seed: rand::thread_rng().gen_range(1.0..10.0),
-            pixels: 100.0,
-            light_origin: Vec2::new(rand::random(), rand::random()),
-            color_texture: Some(asset_server.add(options.colorscheme.gradient_image_with_bg().0)),
-            position: options.screen_size.random_postion(2.0),
+            seed: config::sample(rng, &options.planet_settings.seed),
+            pixels: options.planet_settings.pixels,
+            light_origin: Vec2::new(rng.gen(), rng.gen()),
+            color_texture: Some(asset_server.add(palette.gradient_image_with_bg().0)),
+            position: options.screen_size.random_postion(rng, 2.0),
+            kind: kind as i32,
+            scale: 1.0,
+            tilt: 0.0,
+            time: 0.0,
+            blend: options.blend_modes.planets,
         }
     }
 }
 
-impl Material2d for PlanetsMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/output/planets.frag".into()
-    }
-
-    fn vertex_shader() -> ShaderRef {
-        "shaders/output/planets.vert".into()
+impl ScaledMaterial2D for PlanetsMaterial {
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
     }
+}
 
-    fn specialize(
-        descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
-        _: &bevy::render::mesh::MeshVertexBufferLayoutRef,
-        _: bevy::sprite::Material2dKey<Self>,
-    ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        descriptor.vertex.entry_point = "main".into();
-        descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
-        Ok(())
+impl ParallaxMaterial2D for PlanetsMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
     }
 }
 
-#[derive(Component)]
+layers::blended_material!(PlanetsMaterial, "planets");
+
+#[derive(Component, AnimatedMaterialConfig)]
 pub struct PlanetsConfig {
-    default: DefaultAnimationConfig,
+    #[animation]
+    tracks: Tracks,
 }
 
 impl PlanetsConfig {
-    fn new() -> Self {
-        PlanetsConfig {
-            default: DefaultAnimationConfig::default(),
-        }
-    }
-}
-
-impl AnimatedMaterialConfig for PlanetsConfig {
-    fn start(&mut self, start: f32, target: f32) {
-        self.default.start(start, target);
-    }
-
-    fn progress(&self) -> f32 {
-        self.default.progress()
-    }
-
-    fn update_progress(&mut self, new_progress: f32) {
-        self.default.update_progress(new_progress);
-    }
-
-    fn target(&self) -> f32 {
-        self.default.target()
-    }
-
-    fn change_direction(&mut self) {
-        self.default.change_direction()
-    }
-
-    fn cycle(&self) -> bool {
-        self.default.cycle()
-    }
-
-    fn speed(&self) -> f32 {
-        self.default.speed()
+    pub fn new(tracks: Tracks) -> Self {
+        PlanetsConfig { tracks }
     }
 }
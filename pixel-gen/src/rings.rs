@@ -0,0 +1,120 @@
+use bevy::{reflect::TypePath, render::render_resource::AsBindGroup};
+use rand::Rng;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    planets::PlanetsMaterial,
+    shaders::ScaledMaterial2D,
+    *,
+};
+
+/// How far out from the planet's surface the rings start, in planet radii.
+const GAP: f32 = 0.25;
+
+/// One half of a planet's rings; the back half is drawn behind the planet and
+/// the front half over it.
+#[derive(Component)]
+pub struct Ring;
+
+/// Draws the rings for `planet` from `rng`, or `None` if it has none. Every
+/// value is drawn either way so the planets after it are unaffected.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+    rng: &mut impl Rng,
+    planet: &PlanetsMaterial,
+    radius: f32,
+) -> Option<RingMaterial> {
+    let settings = &options.planet_settings;
+    let has_rings = rng.gen::<f32>() < settings.rings;
+    let width = config::sample(rng, &settings.ring_width);
+    let tilt = config::sample(rng, &settings.ring_tilt);
+    let inclination = config::sample(rng, &settings.ring_inclination);
+    let seed = config::sample(rng, &settings.seed);
+
+    has_rings.then(|| {
+        let inner = 1.0 + GAP;
+        let outer = inner + width.max(0.0);
+        RingMaterial {
+            pixels: planet.pixels * outer,
+            inner: inner / outer,
+            tilt: tilt.to_radians(),
+            inclination: inclination.clamp(0.05, 1.0),
+            seed,
+            front: 0,
+            color_texture: Some(images.add(palette.gradient_image_with_bg().0)),
+            position: planet.position,
+            scale: 1.0,
+            radius: radius * outer,
+            blend: planet.blend,
+        }
+    })
+}
+
+/// World space covered by the ring's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &RingMaterial) -> Rect {
+    Rect::from_center_size(
+        material.position.truncate(),
+        Vec2::splat(material.radius * 2.),
+    )
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(BlendMode)]
+pub struct RingMaterial {
+    #[uniform(0)]
+    pub(crate) pixels: f32,
+    /// Inner edge, as a fraction of the outer radius.
+    #[uniform(1)]
+    pub(crate) inner: f32,
+    #[uniform(2)]
+    pub(crate) tilt: f32,
+    /// How squashed the ring is seen from here; 1 is face on.
+    #[uniform(3)]
+    pub(crate) inclination: f32,
+    #[uniform(4)]
+    pub(crate) seed: f32,
+    #[uniform(5)]
+    pub(crate) front: i32,
+
+    #[texture(6)]
+    #[sampler(7)]
+    pub(crate) color_texture: Option<Handle<Image>>,
+    #[uniform(8)]
+    pub(crate) position: Vec3,
+    #[uniform(9)]
+    pub(crate) scale: f32,
+
+    /// Outer radius in world space, for the mesh.
+    pub(crate) radius: f32,
+
+    pub(crate) blend: BlendMode,
+}
+
+impl RingMaterial {
+    /// The back and front halves.
+    pub(crate) fn halves(&self) -> [RingMaterial; 2] {
+        [
+            self.clone(),
+            RingMaterial {
+                front: 1,
+                ..self.clone()
+            },
+        ]
+    }
+}
+
+impl ScaledMaterial2D for RingMaterial {
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+}
+
+impl ParallaxMaterial2D for RingMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
+    }
+}
+
+layers::blended_material!(RingMaterial, "rings");
@@ -0,0 +1,391 @@
+//! Keyframed animation of a material's uniforms, in seconds.
+
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use super::AnimatedMaterialConfig;
+
+/// How an [`Animation`] moves from one keyframe to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Holds the previous value until the keyframe is reached.
+    Step,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicInOut,
+    SineInOut,
+}
+
+impl Easing {
+    /// Maps progress `t` between two keyframes, 0 to 1, to how far the value
+    /// has moved between them.
+    pub fn ease(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::Step => (t >= 1.) as u8 as f32,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => t * (2. - t),
+            Easing::QuadInOut if t < 0.5 => 2. * t * t,
+            Easing::QuadInOut => 1. - (-2. * t + 2.).powi(2) / 2.,
+            Easing::CubicInOut if t < 0.5 => 4. * t * t * t,
+            Easing::CubicInOut => 1. - (-2. * t + 2.).powi(3) / 2.,
+            Easing::SineInOut => (1. - (t * std::f32::consts::PI).cos()) / 2.,
+        }
+    }
+}
+
+/// What an [`Animation`] does once it reaches its last keyframe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Holds the last keyframe.
+    Once,
+    /// Jumps back to the first; seamless when the last keyframe matches it.
+    #[default]
+    Loop,
+    /// Plays backwards to the first, then forwards again.
+    PingPong,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// When the value is reached, as a fraction of the animation's duration.
+    pub at: f32,
+    pub value: f32,
+    /// How the value eases in from the keyframe before.
+    pub easing: Easing,
+}
+
+/// Keyframes played over `duration` seconds. Holds its own clock, so each
+/// entity's animation runs independently of the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+    pub duration: f32,
+    pub repeat: Repeat,
+    elapsed: f32,
+}
+
+impl Animation {
+    pub fn new(duration: f32, repeat: Repeat) -> Self {
+        Animation {
+            keyframes: Vec::new(),
+            duration,
+            repeat,
+            elapsed: 0.,
+        }
+    }
+
+    /// Goes from `start` to `end` over `duration` seconds.
+    pub fn between(start: f32, end: f32, duration: f32, repeat: Repeat, easing: Easing) -> Self {
+        Animation::new(duration, repeat)
+            .with_keyframe(0., start, Easing::Linear)
+            .with_keyframe(1., end, easing)
+    }
+
+    /// Adds a keyframe `at` a fraction of the duration, replacing any already
+    /// there.
+    pub fn with_keyframe(mut self, at: f32, value: f32, easing: Easing) -> Self {
+        let keyframe = Keyframe { at, value, easing };
+        match self.keyframes.iter().position(|k| k.at >= at) {
+            Some(i) if self.keyframes[i].at == at => self.keyframes[i] = keyframe,
+            Some(i) => self.keyframes.insert(i, keyframe),
+            None => self.keyframes.push(keyframe),
+        }
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Seconds played so far, into the current cycle once it comes back round.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    /// Jumps to `elapsed` seconds in, e.g. to start animations out of step.
+    pub fn set_elapsed(&mut self, elapsed: f32) {
+        self.elapsed = elapsed;
+    }
+
+    /// Where in the keyframes the animation is, from 0 to 1.
+    fn phase(&self) -> f32 {
+        if self.duration <= 0. {
+            return 1.;
+        }
+
+        let t = self.elapsed / self.duration;
+        match self.repeat {
+            Repeat::Once => t.clamp(0., 1.),
+            Repeat::Loop => t.rem_euclid(1.),
+            Repeat::PingPong => {
+                let t = t.rem_euclid(2.);
+                if t > 1. {
+                    2. - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+
+    /// Seconds until the animation is back where it started, `None` for a
+    /// `Once` animation, which never is.
+    fn cycle(&self) -> Option<f32> {
+        match self.repeat {
+            Repeat::Once => None,
+            Repeat::Loop => Some(self.duration),
+            Repeat::PingPong => Some(self.duration * 2.),
+        }
+        .filter(|&cycle| cycle > 0.)
+    }
+
+    /// Moves the animation on by `delta` seconds. Repeating animations wrap
+    /// round at the end of each cycle, so they keep the precision to take
+    /// small steps however long they run.
+    pub fn advance(&mut self, delta: f32) {
+        match self.cycle() {
+            Some(cycle) => {
+                self.elapsed = (self.elapsed + delta.rem_euclid(cycle)).rem_euclid(cycle)
+            }
+            None => self.elapsed += delta,
+        }
+    }
+
+    /// Stretches the duration so a whole number of cycles, at least one, fit
+    /// in `seconds`, keeping the animation's place in its cycle. A `Once`
+    /// animation never comes back round, so it is left alone.
+    pub fn fit(&mut self, seconds: f32) {
+        let Some(cycle) = self.cycle() else {
+            return;
+        };
+        if seconds <= 0. {
+            return;
+        }
+
+        let fitted = seconds / (seconds / cycle).round().max(1.);
+        self.duration *= fitted / cycle;
+        self.elapsed *= fitted / cycle;
+    }
+
+    /// Whether a `Once` animation has reached its last keyframe.
+    pub fn finished(&self) -> bool {
+        self.repeat == Repeat::Once && self.elapsed >= self.duration
+    }
+
+    /// The value at the current time, or 0 without keyframes.
+    pub fn sample(&self) -> f32 {
+        let phase = self.phase();
+        let next = self.keyframes.iter().position(|k| k.at > phase);
+        match next {
+            None => self.keyframes.last().map_or(0., |k| k.value),
+            Some(0) => self.keyframes[0].value,
+            Some(i) => {
+                let (from, to) = (self.keyframes[i - 1], self.keyframes[i]);
+                let t = (phase - from.at) / (to.at - from.at);
+                from.value.lerp(to.value, to.easing.ease(t))
+            }
+        }
+    }
+}
+
+/// Runs a shader's `time` from 0 to 1 every `period` seconds, for shaders that
+/// come back round to where they started at 1, so wrapping to 0 is seamless.
+pub(crate) fn cycle(period: f32) -> Animation {
+    Animation::between(0., 1., period, Repeat::Loop, Easing::Linear)
+}
+
+/// Animations of a material's uniforms, each on its own track, named after
+/// the uniform: `size`, or `light_origin.x` for a component of a vector.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct Tracks {
+    tracks: Vec<(String, Animation)>,
+}
+
+impl Tracks {
+    pub fn new() -> Self {
+        Tracks::default()
+    }
+
+    /// Animates `track`, replacing whatever animated it before.
+    pub fn with(mut self, track: impl Into<String>, animation: Animation) -> Self {
+        let track = track.into();
+        match self.tracks.iter_mut().find(|(name, _)| *name == track) {
+            Some((_, existing)) => *existing = animation,
+            None => self.tracks.push((track, animation)),
+        }
+        self
+    }
+
+    /// Moves the vector uniform `track` round a circle about `centre`, through
+    /// `point`, once every `duration` seconds.
+    pub fn with_orbit(self, track: &str, centre: Vec2, point: Vec2, duration: f32) -> Self {
+        let offset = point - centre;
+        let (radius, angle) = (offset.length(), offset.to_angle());
+        // a sine eased swing from one side to the other traces a cosine
+        let cosine = |centre: f32, phase: f32| {
+            let mut animation = Animation::new(duration, Repeat::Loop)
+                .with_keyframe(0., centre + radius, Easing::Linear)
+                .with_keyframe(0.5, centre - radius, Easing::SineInOut)
+                .with_keyframe(1., centre + radius, Easing::SineInOut);
+            animation.set_elapsed(phase.rem_euclid(TAU) / TAU * duration);
+            animation
+        };
+
+        self.with(format!("{track}.x"), cosine(centre.x, angle))
+            .with(format!("{track}.y"), cosine(centre.y, angle - TAU / 4.))
+    }
+
+    /// Fits every track to loop within `seconds`, see [`Animation::fit`].
+    pub fn fit(&mut self, seconds: f32) {
+        for (_, animation) in &mut self.tracks {
+            animation.fit(seconds);
+        }
+    }
+
+    pub fn get(&self, track: &str) -> Option<&Animation> {
+        self.iter().find(|(name, _)| *name == track).map(|(_, a)| a)
+    }
+
+    pub fn get_mut(&mut self, track: &str) -> Option<&mut Animation> {
+        self.tracks
+            .iter_mut()
+            .find(|(name, _)| name == track)
+            .map(|(_, animation)| animation)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Animation)> {
+        self.tracks
+            .iter()
+            .map(|(name, animation)| (name.as_str(), animation))
+    }
+}
+
+impl AnimatedMaterialConfig for Tracks {
+    fn advance(&mut self, delta: f32) {
+        for (_, animation) in &mut self.tracks {
+            animation.advance(delta);
+        }
+    }
+
+    fn values(&self) -> Vec<(&str, f32)> {
+        self.iter()
+            .map(|(name, animation)| (name, animation.sample()))
+            .collect()
+    }
+
+    fn finished(&self) -> bool {
+        self.tracks
+            .iter()
+            .all(|(_, animation)| animation.finished())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(mut animation: Animation, elapsed: f32) -> f32 {
+        animation.set_elapsed(elapsed);
+        animation.sample()
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn samples_between_keyframes() {
+        let animation = Animation::new(4., Repeat::Once)
+            .with_keyframe(0., 0., Easing::Linear)
+            .with_keyframe(0.5, 10., Easing::Linear)
+            .with_keyframe(1., 20., Easing::QuadIn);
+
+        assert_near(at(animation.clone(), 0.), 0.);
+        assert_near(at(animation.clone(), 1.), 5.);
+        assert_near(at(animation.clone(), 2.), 10.);
+        // a quarter of the way through a quadratic ease in
+        assert_near(at(animation.clone(), 3.), 10. + 10. * 0.25);
+        assert_near(at(animation, 4.), 20.);
+    }
+
+    #[test]
+    fn step_holds_until_the_keyframe() {
+        let animation = Animation::between(1., 3., 2., Repeat::Once, Easing::Step);
+
+        assert_near(at(animation.clone(), 0.), 1.);
+        assert_near(at(animation.clone(), 1.99), 1.);
+        assert_near(at(animation, 2.), 3.);
+    }
+
+    #[test]
+    fn wraps_at_the_period() {
+        let animation = |repeat| Animation::between(0., 1., 2., repeat, Easing::Linear);
+
+        assert_near(at(animation(Repeat::Loop), 2.5), 0.25);
+        assert_near(at(animation(Repeat::Loop), -0.5), 0.75);
+        assert_near(at(animation(Repeat::PingPong), 2.5), 0.75);
+        assert_near(at(animation(Repeat::PingPong), 4.5), 0.25);
+        assert_near(at(animation(Repeat::Once), 2.5), 1.);
+        assert!(!animation(Repeat::Loop).finished());
+    }
+
+    #[test]
+    fn set_elapsed_jumps_and_advance_moves_on() {
+        let mut animation = Animation::between(0., 4., 4., Repeat::Once, Easing::Linear);
+        animation.set_elapsed(1.);
+        assert_near(animation.elapsed(), 1.);
+        assert_near(animation.sample(), 1.);
+
+        animation.advance(3.);
+        assert_near(animation.sample(), 4.);
+        assert!(animation.finished());
+    }
+
+    #[test]
+    fn advance_wraps_so_long_runs_keep_their_precision() {
+        for repeat in [Repeat::Loop, Repeat::PingPong] {
+            let mut animation = Animation::between(0., 1., 2., repeat, Easing::Linear);
+            animation.set_elapsed(0.1);
+            let start = animation.sample();
+
+            // hours of whole cycles, then a step far smaller than an f32
+            // counting the seconds could still take
+            let cycle = if repeat == Repeat::Loop { 2. } else { 4. };
+            for _ in 0..9000 {
+                animation.advance(cycle);
+            }
+            assert_near(animation.sample(), start);
+            animation.advance(36_000.);
+            animation.advance(0.001);
+            assert_near(animation.elapsed(), 0.101);
+        }
+    }
+
+    #[test]
+    fn fit_loops_a_whole_number_of_times() {
+        let mut animation = cycle(3.);
+        animation.set_elapsed(1.5);
+        animation.fit(8.);
+        // 8 / 3 rounds to 3 loops of 8 / 3 seconds, at the same place in one
+        assert_near(animation.duration, 8. / 3.);
+        assert_near(animation.sample(), 0.5);
+
+        let mut ping_pong = Animation::between(0., 1., 3., Repeat::PingPong, Easing::Linear);
+        ping_pong.fit(8.);
+        // there and back is the cycle, 6 seconds fitted to 8
+        assert_near(ping_pong.duration, 4.);
+
+        let mut once = Animation::between(0., 1., 3., Repeat::Once, Easing::Linear);
+        once.fit(8.);
+        assert_near(once.duration, 3.);
+    }
+}
@@ -1,83 +1,126 @@
+mod animation;
+
 use bevy::{prelude::*, sprite::Material2d};
 
 use crate::config::Options;
 
+pub(crate) use animation::cycle;
+pub use animation::{Animation, Easing, Keyframe, Repeat, Tracks};
+
+/// A material whose uniforms can be animated, each by name: `size`, or
+/// `light_origin.x` for a component of a vector. Usually derived, with
+/// `#[animate]` on the uniforms.
 pub trait AnimatedMaterial2D: Material2d {
-    fn get(&self) -> f32;
-    fn update(&mut self, new_value: f32);
+    /// The current value of `track`, or `None` if the material has no such
+    /// uniform.
+    fn get(&self, track: &str) -> Option<f32>;
+    /// Sets `track`; tracks the material doesn't have are ignored.
+    fn update(&mut self, track: &str, value: f32);
 }
 
-// Could make this a single trait and have everything in the material.
-
-pub trait AnimatedMaterialConfig: Component {
-    fn start(&mut self, start: f32, target: f32);
-
-    fn progress(&self) -> f32;
-    fn update_progress(&mut self, new_progress: f32);
+/// A uniform type `#[animate]` can drive: scalars whole, vectors a component
+/// at a time.
+pub trait AnimatedUniform {
+    fn component(&self, component: Option<&str>) -> Option<f32>;
+    fn set_component(&mut self, component: Option<&str>, value: f32);
+}
 
-    fn target(&self) -> f32;
+impl AnimatedUniform for f32 {
+    fn component(&self, component: Option<&str>) -> Option<f32> {
+        component.is_none().then_some(*self)
+    }
 
-    fn change_direction(&mut self);
+    fn set_component(&mut self, component: Option<&str>, value: f32) {
+        if component.is_none() {
+            *self = value;
+        }
+    }
+}
 
-    fn cycle(&self) -> bool;
+/// Rounded to the nearest whole number, for uniforms that pick a sprite or a
+/// mode.
+impl AnimatedUniform for i32 {
+    fn component(&self, component: Option<&str>) -> Option<f32> {
+        component.is_none().then_some(*self as f32)
+    }
 
-    fn speed(&self) -> f32;
+    fn set_component(&mut self, component: Option<&str>, value: f32) {
+        if component.is_none() {
+            *self = value.round() as i32;
+        }
+    }
 }
 
-#[derive(Component, Default)]
-pub struct DefaultAnimationConfig {
-    pub progress: f32,
-    pub start: f32,
-    pub target: f32,
-    pub direction: bool,
+macro_rules! animated_vector {
+    ($vector:ty, $($axis:ident),+) => {
+        impl AnimatedUniform for $vector {
+            fn component(&self, component: Option<&str>) -> Option<f32> {
+                match component {
+                    $(Some(stringify!($axis)) => Some(self.$axis),)+
+                    _ => None,
+                }
+            }
+
+            fn set_component(&mut self, component: Option<&str>, value: f32) {
+                match component {
+                    $(Some(stringify!($axis)) => self.$axis = value,)+
+                    _ => {}
+                }
+            }
+        }
+    };
 }
 
-impl AnimatedMaterialConfig for DefaultAnimationConfig {
-    fn start(&mut self, start: f32, target: f32) {
-        self.start = start;
-        self.progress = start;
-        self.target = target;
-        self.direction = true;
-        self.change_direction();
-    }
+animated_vector!(Vec2, x, y);
+animated_vector!(Vec3, x, y, z);
+animated_vector!(Vec4, x, y, z, w);
 
-    fn progress(&self) -> f32 {
-        self.progress
+/// Splits a track name into its uniform and, for vectors, the component:
+/// `light_origin.x` into `("light_origin", Some("x"))`.
+pub fn split_track(track: &str) -> (&str, Option<&str>) {
+    match track.split_once('.') {
+        Some((field, component)) => (field, Some(component)),
+        None => (track, None),
     }
+}
 
-    fn update_progress(&mut self, new_progress: f32) {
-        self.progress = new_progress;
-    }
+/// Drives the uniforms `animate_material` writes into an `AnimatedMaterial2D`.
+pub trait AnimatedMaterialConfig: Component {
+    /// Moves every track on by `delta` seconds.
+    fn advance(&mut self, delta: f32);
 
-    fn target(&self) -> f32 {
-        if self.direction {
-            self.target
-        } else {
-            self.start
-        }
-    }
+    /// Each track's name and the value its uniform should have now.
+    fn values(&self) -> Vec<(&str, f32)>;
 
-    fn change_direction(&mut self) {
-        self.direction = !self.direction;
+    /// Whether the animation has stopped for good.
+    fn finished(&self) -> bool {
+        false
     }
+}
 
-    fn cycle(&self) -> bool {
-        if self.direction {
-            self.progress >= self.target()
-        } else {
-            self.progress <= self.target()
-        }
-    }
+/// Materials whose vertex shader places the mesh from a `position` uniform
+/// instead of the transform, so the transform's scale has to be passed in.
+pub trait ScaledMaterial2D: Material2d {
+    fn set_scale(&mut self, scale: f32);
+}
 
-    fn speed(&self) -> f32 {
-        0.01
+pub fn scale_material<M: ScaledMaterial2D>(
+    query: Query<(&Handle<M>, &GlobalTransform), Changed<GlobalTransform>>,
+    mut material_assets: ResMut<Assets<M>>,
+) {
+    for (handle, transform) in query.iter() {
+        let Some(material) = material_assets.get_mut(handle.id()) else {
+            continue;
+        };
+
+        material.set_scale(transform.compute_transform().scale.x);
     }
 }
 
 pub fn animate_material<M, C>(
     time: Res<Time>,
     options: Res<Options>,
-    mut material: Query<(&mut Handle<M>, &mut C)>,
+    mut material: Query<(&Handle<M>, &mut C)>,
     mut material_assets: ResMut<Assets<M>>,
 ) where
     M: AnimatedMaterial2D,
@@ -88,18 +131,16 @@ pub fn animate_material<M, C>(
     }
 
     for (handle, mut config) in material.iter_mut() {
+        if config.finished() {
+            continue;
+        }
         let Some(material) = material_assets.get_mut(handle.id()) else {
-            return;
+            continue;
         };
 
-        if config.cycle() {
-            config.change_direction();
+        config.advance(time.delta_seconds());
+        for (track, value) in config.values() {
+            material.update(track, value);
         }
-
-        material.update(
-            material
-                .get()
-                .lerp(config.target(), time.delta_seconds() * config.speed()),
-        );
     }
 }
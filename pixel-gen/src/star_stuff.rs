@@ -1,189 +1,234 @@
-use bevy::{
-    reflect::TypePath,
-    render::render_resource::{AsBindGroup, ShaderRef},
-};
+use std::ops::Range;
+
+use bevy::{ecs::system::SystemParam, reflect::TypePath, render::render_resource::AsBindGroup};
 use rand::Rng;
+use serde::Deserialize;
 
 use crate::{
-    shaders::{AnimatedMaterial2D, AnimatedMaterialConfig, DefaultAnimationConfig},
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    shaders::{cycle, Animation, Easing, Repeat, Tracks},
     *,
 };
 
-#[derive(Event)]
-pub struct SpawnStarStuffEvent;
-
 #[derive(Component)]
 pub struct StarStuff;
 
-pub fn setup(mut trigger: EventWriter<SpawnStarStuffEvent>) {
-    trigger.send(SpawnStarStuffEvent);
-}
-
-pub fn spawn_star_stuff(
-    mut trigger: EventReader<SpawnStarStuffEvent>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StarStuffMaterial>>,
-    options: Res<config::Options>,
-    current_nebulae: Query<Entity, With<StarStuff>>,
-    mut asset_server: ResMut<Assets<Image>>,
-) {
-    let Some(_) = trigger.read().next() else {
-        return;
-    };
-    trigger.clear();
+/// Seconds the noise takes to zoom in, before zooming back out, with `animate`.
+const ZOOM_SECONDS: f32 = 60.;
+/// Seconds the dust takes to drift across, before drifting back.
+const DRIFT_SECONDS: f32 = 120.;
 
-    if let Ok(entity) = current_nebulae.get_single() {
-        commands.entity(entity).despawn_recursive();
-    }
+#[derive(SystemParam)]
+pub struct StarStuffSpawner<'w> {
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<StarStuffMaterial>>,
+    images: ResMut<'w, Assets<Image>>,
+}
 
-    if !options.dust {
-        return;
+impl BackgroundLayer for StarStuff {
+    const NAME: &'static str = "dust";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyD);
+
+    type Param = StarStuffSpawner<'static>;
+
+    fn spawn(
+        commands: &mut Commands,
+        options: &config::ResolvedOptions,
+        ss: &mut StarStuffSpawner,
+    ) {
+        let mat = generate(options, options.palette(), &mut ss.images);
+
+        let drift = |start: f32, end: f32, seconds: f32| {
+            Animation::between(start, end, seconds, Repeat::PingPong, Easing::SineInOut)
+        };
+        let mut tracks = Tracks::new()
+            .with("size", drift(mat.size, mat.size + 1., ZOOM_SECONDS))
+            .with("time", cycle(options.dust_settings.flow_period));
+        // the dust drifts across the scene, unless parallax mode scrolls it
+        if !options.parallax {
+            tracks = tracks
+                .with("scroll.x", drift(0., 0.05, DRIFT_SECONDS))
+                .with("scroll.y", drift(0., 0.03, DRIFT_SECONDS * 0.7));
+        }
+        let animation_config = StarStuffConfig::new(tracks);
+        let layer = ParallaxLayer::new(
+            options.parallax_settings.dust,
+            mat.position,
+            options.screen_size.rect(),
+        );
+
+        commands.spawn((
+            StarStuff,
+            animation_config,
+            layer,
+            MaterialMesh2dBundle {
+                mesh: ss
+                    .meshes
+                    .add(Rectangle::from_size(quad(options).size()))
+                    .into(),
+                material: ss.materials.add(mat),
+                transform: Transform::from_xyz(0., 0., options.layer_z.dust),
+                ..default()
+            },
+        ));
+    }
+
+    fn enabled(options: &config::Options) -> bool {
+        options.dust
+    }
+
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.dust = enabled;
     }
+}
 
-    let mat = StarStuffMaterial::new(
-        &options,
-        &mut asset_server,
+/// The dust material for `options`, drawn from the "dust" seed stream.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+) -> StarStuffMaterial {
+    StarStuffMaterial::new(
+        options,
+        palette,
+        images,
+        &mut options.seed.stream("dust"),
         options.screen_size.x_offset(),
         &options.screen_size,
-    );
-
-    let mut animation_config = StarStuffConfig::new();
-
-    animation_config.start(mat.get(), mat.get() + 1.);
-
-    commands.spawn((
-        StarStuff,
-        animation_config,
-        MaterialMesh2dBundle {
-            mesh: meshes
-                .add(Rectangle::from_size(Vec2::new(
-                    options.screen_size.width(),
-                    options.screen_size.height(),
-                )))
-                .into(),
-            material: materials.add(mat),
-            ..default()
-        },
-    ));
+    )
 }
 
-#[derive(Component)]
-pub struct StarStuffConfig {
-    default: DefaultAnimationConfig,
+/// World space covered by the dust mesh once the vertex shader offsets it.
+pub(crate) fn quad(options: &config::Options) -> Rect {
+    Rect::from_center_size(
+        Vec2::new(options.screen_size.x_offset(), 0.),
+        Vec2::new(options.screen_size.width(), options.screen_size.height()),
+    )
 }
 
-impl StarStuffConfig {
-    pub fn new() -> Self {
-        StarStuffConfig {
-            default: DefaultAnimationConfig::default(),
-        }
-    }
+/// Ranges the dust's noise is drawn from.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DustSettings {
+    /// Octaves of fbm noise; more is more detailed.
+    pub octaves: Range<i32>,
+    pub seed: Range<f32>,
+    /// How far the dust is drifted as it flows, reshaping its wisps, in noise
+    /// cells; 0 leaves the dust still.
+    pub flow: f32,
+    /// Seconds the flow takes to come back round to where it started, with
+    /// `animate`.
+    pub flow_period: f32,
 }
 
-impl AnimatedMaterialConfig for StarStuffConfig {
-    fn start(&mut self, start: f32, target: f32) {
-        self.default.start(start, target);
-    }
-
-    fn progress(&self) -> f32 {
-        self.default.progress()
-    }
-
-    fn update_progress(&mut self, new_progress: f32) {
-        self.default.update_progress(new_progress);
-    }
-
-    fn target(&self) -> f32 {
-        self.default.target()
-    }
+impl DustSettings {
+    pub const DEFAULT: DustSettings = DustSettings {
+        octaves: 3..5,
+        seed: 1.0..50.0,
+        flow: 0.5,
+        flow_period: 60.0,
+    };
+}
 
-    fn change_direction(&mut self) {
-        self.default.change_direction()
+impl Default for DustSettings {
+    fn default() -> Self {
+        Self::DEFAULT
     }
+}
 
-    fn cycle(&self) -> bool {
-        self.default.cycle()
-    }
+#[derive(Component, AnimatedMaterialConfig)]
+pub struct StarStuffConfig {
+    #[animation]
+    tracks: Tracks,
+}
 
-    fn speed(&self) -> f32 {
-        self.default.speed()
+impl StarStuffConfig {
+    pub fn new(tracks: Tracks) -> Self {
+        StarStuffConfig { tracks }
     }
 }
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
 pub struct StarStuffMaterial {
     #[uniform(0)]
-    size: f32,
+    #[animate]
+    pub(crate) size: f32,
     #[uniform(3)]
-    octaves: i32,
+    pub(crate) octaves: i32,
     #[uniform(4)]
-    seed: f32,
+    pub(crate) seed: f32,
     #[uniform(5)]
-    pixels: f32,
+    pub(crate) pixels: f32,
     #[uniform(6)]
-    uv_correct: Vec2,
+    pub(crate) uv_correct: Vec2,
     #[uniform(7)]
-    should_tile: i32,
+    pub(crate) should_tile: i32,
     #[uniform(8)]
-    reduce_background: i32,
+    pub(crate) reduce_background: i32,
     #[uniform(9)]
-    position: Vec3,
+    pub(crate) position: Vec3,
+    #[uniform(10)]
+    pub(crate) transparent: i32,
+    /// Offset of the texture in parallax mode, in uv.
+    #[uniform(11)]
+    #[animate]
+    pub(crate) scroll: Vec2,
+    /// How far through the flow's loop the dust is, from 0 to 1.
+    #[uniform(12)]
+    #[animate]
+    pub(crate) time: f32,
+    #[uniform(13)]
+    pub(crate) flow: f32,
 
     #[texture(1)]
     #[sampler(2)]
-    color_texture: Option<Handle<Image>>,
-}
-
-impl AnimatedMaterial2D for StarStuffMaterial {
-    fn get(&self) -> f32 {
-        self.size
-    }
+    pub(crate) color_texture: Option<Handle<Image>>,
 
-    fn update(&mut self, new_value: f32) {
-        self.size = new_value
-    }
+    pub(crate) blend: BlendMode,
 }
 
 impl StarStuffMaterial {
     fn new(
         options: &config::Options,
+        palette: &Palette,
         asset_server: &mut Assets<Image>,
+        rng: &mut impl Rng,
         x_offset: f32,
         screen_size: &config::ScreenSize,
     ) -> Self {
-        let mut rng = rand::thread_rng();
+        let should_tile = options.tile || options.parallax;
+        let (size, pixels) = (screen_size.width() / options.pixels, options.pixels);
+        let (size, pixels) = if should_tile {
+            tile::snap_noise(size, pixels)
+        } else {
+            (size, pixels)
+        };
 
         StarStuffMaterial {
-            size: screen_size.width() / options.pixels,
-            octaves: rng.gen_range(3..5),
-            seed: rng.gen_range(1.0..50.0),
-            pixels: options.pixels,
+            size,
+            octaves: config::sample(rng, &options.dust_settings.octaves),
+            seed: config::sample(rng, &options.dust_settings.seed),
+            pixels,
             uv_correct: options.screen_size.aspect(),
-            color_texture: Some(asset_server.add(options.colorscheme.gradient_image_with_bg().0)),
-            should_tile: options.tile as i32,
+            color_texture: Some(asset_server.add(palette.gradient_image_with_bg().0)),
+            should_tile: should_tile as i32,
             reduce_background: options.darken as i32,
+            transparent: options.transparency as i32,
             position: Vec3::new(x_offset, 0., 0.),
+            scroll: Vec2::ZERO,
+            time: 0.,
+            flow: options.dust_settings.flow,
+            blend: options.blend_modes.dust,
         }
     }
 }
 
-impl Material2d for StarStuffMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/output/star_stuff.frag".into()
-    }
-
-    fn vertex_shader() -> ShaderRef {
-        "shaders/output/star_stuff.vert".into()
-    }
-
-    fn specialize(
-        descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
-        _: &bevy::render::mesh::MeshVertexBufferLayoutRef,
-        _: bevy::sprite::Material2dKey<Self>,
-    ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        descriptor.vertex.entry_point = "main".into();
-        descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
-        Ok(())
+impl ParallaxMaterial2D for StarStuffMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.origin() + anchor.extend(0.);
+        self.scroll = (offset / layer.scene().size() * Vec2::new(1., -1.)).rem_euclid(Vec2::ONE);
     }
 }
+
+layers::blended_material!(StarStuffMaterial, "star_stuff");
@@ -1,75 +1,255 @@
-use bevy::{
-    ecs::system::SystemParam,
-    render::render_resource::{AsBindGroup, ShaderRef},
-};
+use std::ops::Range;
+
+use bevy::{ecs::system::SystemParam, render::render_resource::AsBindGroup};
 use rand::Rng;
+use serde::Deserialize;
+
+use crate::{
+    parallax::{ParallaxLayer, ParallaxMaterial2D},
+    shaders::{Animation, Easing, Repeat, Tracks},
+    *,
+};
 
-use crate::*;
+pub(crate) const STAR_SHEET: &str = "stars-special.png";
 
-#[derive(Event)]
-pub struct SpawnBigStarEvent;
+// Two screen pixels per sprite pixel.
+const SIZE: f32 = 24. * 2.;
 
 #[derive(Component)]
 pub struct Star;
 
 #[derive(SystemParam)]
-pub struct StarSpawner<'w, 's> {
-    events: EventReader<'w, 's, SpawnBigStarEvent>,
-    commands: Commands<'w, 's>,
+pub struct StarSpawner<'w> {
     meshes: ResMut<'w, Assets<Mesh>>,
     mats: ResMut<'w, Assets<BigStarMaterial>>,
     images: ResMut<'w, Assets<Image>>,
-    query: Query<'w, 's, Entity, With<Star>>,
     assets: Res<'w, AssetServer>,
-    options: Res<'w, config::Options>,
 }
 
-pub fn spawn_star(mut ss: StarSpawner) {
-    if ss.events.is_empty() {
-        return;
+impl BackgroundLayer for Star {
+    const NAME: &'static str = "stars";
+    const KEY: Option<KeyCode> = Some(KeyCode::KeyS);
+
+    type Param = StarSpawner<'static>;
+
+    fn spawn(commands: &mut Commands, options: &config::ResolvedOptions, ss: &mut StarSpawner) {
+        let star = ss.assets.load(STAR_SHEET);
+
+        for BigStar { material, twinkle } in
+            generate(options, options.palette(), &mut ss.images, star)
+        {
+            let config = StarConfig { tracks: twinkle };
+            let layer = ParallaxLayer::new(
+                options.parallax_settings.stars,
+                material.position,
+                options.screen_size.rect(),
+            )
+            .wrapping(material.position.truncate(), SIZE / 2.);
+            let mesh = MaterialMesh2dBundle {
+                mesh: ss
+                    .meshes
+                    .add(Rectangle::from_size(Vec2::splat(SIZE)))
+                    .into(),
+                material: ss.mats.add(material),
+                transform: Transform::from_xyz(0., 0., options.layer_z.stars),
+                ..default()
+            };
+
+            commands.spawn((mesh, Star, layer, config));
+        }
     }
-    for entity in ss.query.iter() {
-        ss.commands.entity(entity).despawn_recursive()
+
+    fn enabled(options: &config::Options) -> bool {
+        options.stars
     }
 
-    let mut rng = rand::thread_rng();
-
-    for _ in ss.events.read() {
-        let star = ss.assets.load("stars-special.png");
-        let color_gradiant = ss
-            .images
-            .add(ss.options.colorscheme.gradient_image_with_bg().0);
-        let position = ss.options.screen_size.random_postion(1.5);
-        let index = rng.gen_range(0..6);
-
-        let mesh = MaterialMesh2dBundle {
-            mesh: ss
-                .meshes
-                .add(Rectangle::from_size(Vec2::splat(24. * 2.)))
-                .into(),
-            material: ss
-                .mats
-                .add(BigStarMaterial::new(star, color_gradiant, position, index)),
-            ..default()
-        };
-
-        ss.commands.spawn((mesh, Star));
+    fn set_enabled(options: &mut config::Options, enabled: bool) {
+        options.stars = enabled;
     }
 }
 
-#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+/// A big star and how it twinkles with `animate`.
+pub(crate) struct BigStar {
+    pub(crate) material: BigStarMaterial,
+    pub(crate) twinkle: Tracks,
+}
+
+/// Every big star for `options`, drawn from the "stars" seed stream.
+pub(crate) fn generate(
+    options: &config::Options,
+    palette: &Palette,
+    images: &mut Assets<Image>,
+    star: Handle<Image>,
+) -> Vec<BigStar> {
+    let color_gradiant = images.add(palette.gradient_image_with_bg().0);
+
+    scatter(options)
+        .into_iter()
+        .map(|scattered| BigStar {
+            material: BigStarMaterial::new(
+                star.clone(),
+                color_gradiant.clone(),
+                scattered.position,
+                scattered.star_type,
+                options.blend_modes.stars,
+            ),
+            twinkle: scattered.twinkle,
+        })
+        .collect()
+}
+
+/// A big star before it gets a material.
+#[derive(Clone)]
+struct Scattered {
+    position: Vec3,
+    /// Which sprite it uses.
+    star_type: i32,
+    twinkle: Tracks,
+}
+
+/// Where every big star for `options` is, which sprite it uses and how it
+/// twinkles. Twinkles come from their own "star_brightness" stream, so the
+/// stars themselves come out the same, and are drawn before the stars are
+/// wrapped so a star's copies across the edges flicker with it.
+fn scatter(options: &config::Options) -> Vec<Scattered> {
+    let mut rng = options.seed.stream("stars");
+    let mut brightness = options.seed.stream("star_brightness");
+
+    let stars = (0..config::sample(&mut rng, &options.star_settings.count))
+        .map(|_| {
+            let position = options.screen_size.random_postion(&mut rng, 1.5);
+            let star_type = rng.gen_range(0..6);
+            Scattered {
+                position,
+                star_type,
+                twinkle: twinkle(&mut brightness, &options.star_settings, star_type),
+            }
+        })
+        .collect();
+
+    tile::wrapped(
+        options,
+        stars,
+        |star| Rect::from_center_size(star.position.truncate(), Vec2::splat(SIZE)),
+        |star, shift| star.position += shift.extend(0.),
+    )
+}
+
+/// Where every big star for `options` is, without loading any images.
+pub(crate) fn positions(options: &config::Options) -> Vec<Vec2> {
+    scatter(options)
+        .into_iter()
+        .map(|star| star.position.truncate())
+        .collect()
+}
+
+/// How many big stars are scattered over the scene, and how they twinkle.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StarSettings {
+    /// How many big stars there are, drawn from this range.
+    pub count: Range<u32>,
+    /// Seconds a star takes to flicker through its brightnesses and back to
+    /// full, with `animate`.
+    pub twinkle_period: Range<f32>,
+    /// Brightnesses a star flickers between, 1 being the sprite as drawn; each
+    /// step down is a step down the palette.
+    pub twinkle_brightness: Range<f32>,
+    /// Chance, from 0 to 1, that a star now and then flashes its sprite's twin.
+    pub frame_swaps: f32,
+    /// Seconds between a swapping star's flashes.
+    pub frame_swap_period: Range<f32>,
+}
+
+impl StarSettings {
+    pub const DEFAULT: StarSettings = StarSettings {
+        count: 5..50,
+        twinkle_period: 2.0..5.0,
+        twinkle_brightness: 0.6..1.0,
+        frame_swaps: 0.3,
+        frame_swap_period: 6.0..15.0,
+    };
+}
+
+impl Default for StarSettings {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// World space covered by a star's mesh once the vertex shader offsets it.
+pub(crate) fn quad(material: &BigStarMaterial) -> Rect {
+    Rect::from_center_size(material.position.truncate(), Vec2::splat(SIZE))
+}
+
+/// Flickers a star, out of step with the others, with `animate`.
+#[derive(Component, AnimatedMaterialConfig)]
+pub struct StarConfig {
+    #[animation]
+    tracks: Tracks,
+}
+
+/// Brightness steps a star flickers through each twinkle.
+const FLICKERS: Range<u32> = 3..7;
+/// Seconds a star shows its twin's sprite for.
+const SWAP_SECONDS: f32 = 0.2;
+
+/// A star's `brightness` and, for some, `star_type` tracks, each starting at a
+/// random point so no two stars twinkle together.
+fn twinkle(rng: &mut impl Rng, settings: &StarSettings, star_type: i32) -> Tracks {
+    let seconds = config::sample(rng, &settings.twinkle_period);
+    let flickers = rng.gen_range(FLICKERS);
+    // held steps rather than fades, so the star jumps between palette colours
+    let mut flicker = Animation::new(seconds, Repeat::Loop)
+        .with_keyframe(0., 1., Easing::Linear)
+        .with_keyframe(1., 1., Easing::Step);
+    for i in 1..flickers {
+        let brightness = config::sample(rng, &settings.twinkle_brightness);
+        flicker = flicker.with_keyframe(i as f32 / flickers as f32, brightness, Easing::Step);
+    }
+    flicker.set_elapsed(rng.gen_range(0.0..seconds));
+    let mut tracks = Tracks::new().with("brightness", flicker);
+
+    if rng.gen::<f32>() < settings.frame_swaps {
+        let seconds = config::sample(rng, &settings.frame_swap_period);
+        // the sheet pairs sprites of a size: 0 and 1 are big, 2 and 3
+        // medium, 4 and 5 small
+        let twin = (star_type ^ 1) as f32;
+        let swap = (SWAP_SECONDS / seconds).min(1.);
+        let mut flash = Animation::new(seconds, Repeat::Loop)
+            .with_keyframe(0., twin, Easing::Linear)
+            .with_keyframe(swap, star_type as f32, Easing::Step)
+            .with_keyframe(1., twin, Easing::Step);
+        flash.set_elapsed(rng.gen_range(0.0..seconds));
+        tracks = tracks.with("star_type", flash);
+    }
+
+    tracks
+}
+
+#[derive(Asset, TypePath, AsBindGroup, AnimatedMaterial2D, Debug, Clone)]
+#[bind_group_data(BlendMode)]
 pub struct BigStarMaterial {
     #[texture(1)]
     #[sampler(2)]
-    color_texture: Option<Handle<Image>>,
+    pub(crate) color_texture: Option<Handle<Image>>,
     #[uniform(3)]
-    position: Vec3,
+    pub(crate) position: Vec3,
 
     #[texture(4)]
     #[sampler(5)]
-    image: Option<Handle<Image>>,
+    pub(crate) image: Option<Handle<Image>>,
+    /// Which sprite on the sheet, from 0 to 5.
     #[uniform(6)]
-    star_type: i32,
+    #[animate]
+    pub(crate) star_type: i32,
+    /// Scales the sprite's shade before it is looked up in the palette, so
+    /// dimmer stars step down through its colours.
+    #[uniform(7)]
+    #[animate]
+    pub(crate) brightness: f32,
+
+    pub(crate) blend: BlendMode,
 }
 
 impl BigStarMaterial {
@@ -78,32 +258,23 @@ impl BigStarMaterial {
         color_gradiant: Handle<Image>,
         position: Vec3,
         star_type: i32,
+        blend: BlendMode,
     ) -> Self {
         BigStarMaterial {
             color_texture: Some(color_gradiant),
             position,
             image: Some(star),
             star_type,
+            brightness: 1.,
+            blend,
         }
     }
 }
 
-impl Material2d for BigStarMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/output/big_star.frag".into()
-    }
-
-    fn vertex_shader() -> ShaderRef {
-        "shaders/output/big_star.vert".into()
-    }
-
-    fn specialize(
-        descriptor: &mut bevy::render::render_resource::RenderPipelineDescriptor,
-        _: &bevy::render::mesh::MeshVertexBufferLayoutRef,
-        _: bevy::sprite::Material2dKey<Self>,
-    ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        descriptor.vertex.entry_point = "main".into();
-        descriptor.fragment.as_mut().unwrap().entry_point = "main".into();
-        Ok(())
+impl ParallaxMaterial2D for BigStarMaterial {
+    fn scroll(&mut self, layer: &ParallaxLayer, anchor: Vec2, offset: Vec2) {
+        self.position = layer.position(anchor, offset);
     }
 }
+
+layers::blended_material!(BigStarMaterial, "big_star");
@@ -0,0 +1,191 @@
+//! Tile mode: a scene that repeats seamlessly across all four borders.
+//!
+//! The noise layers wrap their noise coordinates once per tile, which only
+//! lines up when the tile spans a whole number of noise cells and pixels, so
+//! [`snap_noise`] rounds their scale for them. Everything placed in the scene
+//! is repeated one tile over on each side it crosses, by [`wrapped`].
+
+use crate::{cpu::Frame, *};
+
+/// The noise scale and pixel count closest to `size` and `pixels` that repeat
+/// exactly across a tile: the shaders halve `size` for their coarser noise,
+/// and dither alternate pixels.
+pub(crate) fn snap_noise(size: f32, pixels: f32) -> (f32, f32) {
+    let even = |value: f32| (value / 2.).round().max(1.) * 2.;
+    (even(size), even(pixels))
+}
+
+/// In tile mode, moves every item onto the scene, a whole number of scenes
+/// over, and repeats those whose `quad` crosses an edge of it one scene over,
+/// on the opposite side, moving each with `offset`.
+///
+/// Copies follow their item, so overlapping items stack the same way on both
+/// sides of an edge.
+pub(crate) fn wrapped<T: Clone>(
+    options: &config::Options,
+    items: Vec<T>,
+    quad: impl Fn(&T) -> Rect,
+    offset: impl Fn(&mut T, Vec2),
+) -> Vec<T> {
+    if !options.tile {
+        return items;
+    }
+
+    let scene = options.screen_size.rect();
+    let mut wrapped = Vec::with_capacity(items.len());
+    for mut item in items {
+        // anything further than a tile away is moved onto it first, or its
+        // copies would miss the edges it crosses there
+        let home = ((quad(&item).center() - scene.min) / scene.size()).floor() * -scene.size();
+        if home != Vec2::ZERO {
+            offset(&mut item, home);
+        }
+        let copies: Vec<T> = shifts(quad(&item), scene)
+            .into_iter()
+            .map(|shift| {
+                let mut copy = item.clone();
+                offset(&mut copy, shift);
+                copy
+            })
+            .collect();
+        wrapped.push(item);
+        wrapped.extend(copies);
+    }
+    wrapped
+}
+
+/// Where copies of `quad` go so no part of it is cut off by `scene`'s edges,
+/// corners included.
+///
+/// Anything within a pixel of an edge is copied too, so the rim
+/// [`find_seams`] checks has it; those copies are entirely off screen.
+fn shifts(quad: Rect, scene: Rect) -> Vec<Vec2> {
+    let size = scene.size();
+    let axis = |min: f32, max: f32, edge_min: f32, edge_max: f32, size: f32| {
+        let mut shifts = vec![0.];
+        if min < edge_min + 1. {
+            shifts.push(size);
+        }
+        if max > edge_max - 1. {
+            shifts.push(-size);
+        }
+        shifts
+    };
+    let xs = axis(quad.min.x, quad.max.x, scene.min.x, scene.max.x, size.x);
+    let ys = axis(quad.min.y, quad.max.y, scene.min.y, scene.max.y, size.y);
+
+    xs.iter()
+        .flat_map(|&x| ys.iter().map(move |&y| Vec2::new(x, y)))
+        .filter(|shift| *shift != Vec2::ZERO)
+        .collect()
+}
+
+/// Which pair of opposite borders a [`Seam`] runs between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// The right edge against the left.
+    LeftRight,
+    /// The bottom edge against the top.
+    TopBottom,
+}
+
+/// Opposite borders of a tile that do not continue into each other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seam {
+    pub edge: Edge,
+    /// Pixels along the border that differ from the ones the next tile over
+    /// would draw there.
+    pub mismatched: u32,
+    /// The largest difference in any channel.
+    pub largest: f32,
+}
+
+/// Checks a tile rendered with a one pixel rim by
+/// [`headless::render_with_rim`] repeats seamlessly.
+///
+/// The rim past each edge is what the scene draws just beyond it, and in a
+/// tile that is the first row or column of the next tile over: the same as
+/// the opposite edge of this one. A pixel differs from its copy once a
+/// channel is more than `tolerance` apart.
+pub fn find_seams(rimmed: &Frame, tolerance: f32) -> Vec<Seam> {
+    let UVec2 {
+        x: width,
+        y: height,
+    } = rimmed.size.saturating_sub(UVec2::splat(2));
+    let difference = |a: Vec4, b: Vec4| (a - b).abs().max_element();
+    let mut seams = Vec::new();
+
+    if width > 0 {
+        let across = (1..=height).flat_map(|y| {
+            [
+                difference(rimmed.get(0, y), rimmed.get(width, y)),
+                difference(rimmed.get(width + 1, y), rimmed.get(1, y)),
+            ]
+        });
+        seams.extend(seam(Edge::LeftRight, across, tolerance));
+    }
+    if height > 0 {
+        let across = (1..=width).flat_map(|x| {
+            [
+                difference(rimmed.get(x, 0), rimmed.get(x, height)),
+                difference(rimmed.get(x, height + 1), rimmed.get(x, 1)),
+            ]
+        });
+        seams.extend(seam(Edge::TopBottom, across, tolerance));
+    }
+
+    seams
+}
+
+/// `across` holds the difference between each rim pixel and the edge pixel it
+/// should repeat.
+fn seam(edge: Edge, across: impl Iterator<Item = f32>, tolerance: f32) -> Option<Seam> {
+    let (mismatched, largest) = across
+        .filter(|&difference| difference > tolerance)
+        .fold((0, 0f32), |(count, largest), difference| {
+            (count + 1, largest.max(difference))
+        });
+    (mismatched > 0).then_some(Seam {
+        edge,
+        mismatched,
+        largest,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use utils::screenspace::Space;
+
+    use super::*;
+
+    fn rimmed(seed: u32, tile: bool) -> Frame {
+        let options = Options {
+            seed: GenerationSeed(seed),
+            tile,
+            black_hole: true,
+            screen_size: ScreenSize {
+                screen_space: Space {
+                    width: 200.,
+                    height: 120.,
+                },
+                show_ui: false,
+            },
+            ..Options::default()
+        };
+        headless::render_with_rim(&options, &headless::assets_dir()).unwrap()
+    }
+
+    #[test]
+    fn tiled_scene_has_no_seams() {
+        for seed in [1, 5] {
+            assert_eq!(find_seams(&rimmed(seed, true), 1. / 255.), Vec::new());
+        }
+    }
+
+    #[test]
+    fn untiled_scene_has_seams() {
+        let seams = find_seams(&rimmed(1, false), 1. / 255.);
+        let edges: Vec<Edge> = seams.iter().map(|seam| seam.edge).collect();
+        assert_eq!(edges, [Edge::LeftRight, Edge::TopBottom]);
+    }
+}
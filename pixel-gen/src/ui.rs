@@ -32,7 +32,8 @@ pub fn spawn_menu(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     existing_query: Query<Entity, With<Menu>>,
-    options: Res<config::Options>,
+    options: config::ResolvedOptions,
+    layers: Res<LayerRegistry>,
 ) {
     let Some(_) = trigger.read().next() else {
         return;
@@ -43,10 +44,15 @@ pub fn spawn_menu(
         commands.entity(entity).despawn_recursive();
     }
 
-    menu(&asset_server, &options).spawn_with(&mut commands, Menu);
+    menu(&asset_server, &options, options.palette(), &layers).spawn_with(&mut commands, Menu);
 }
 
-fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Element {
+fn menu(
+    asset_server: &AssetServer,
+    options: &config::Options,
+    palette: &Palette,
+    layers: &LayerRegistry,
+) -> gooey_ui::Element {
     let screen_size = options.screen_size;
     Element::Logical(gooey_ui::Config {
         id: "ROOT",
@@ -56,14 +62,9 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
             flex_direction: FlexDirection::Column,
             ..default()
         },
-        children: vec![
+        children: [
             Element::Button {
-                text: ui_text_for_button(
-                    "NEW IMAGE",
-                    asset_server,
-                    options.colorscheme.bg_color(),
-                    "",
-                ),
+                text: ui_text_for_button("NEW IMAGE", asset_server, palette.bg_color(), ""),
                 config: gooey_ui::Config {
                     id: "NEW-IMAGE",
                     style: Style {
@@ -76,6 +77,14 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                     children: vec![],
                 },
             },
+            Element::Text {
+                text: ui_text("SEED:", asset_server, options.seed.to_string().as_str()),
+                config: gooey_ui::Config {
+                    id: "LABEL-SEED",
+                    style: Style::default(),
+                    children: vec![],
+                },
+            },
             Element::Text {
                 text: ui_text("SIZE (PIXELS):", asset_server, ""),
                 config: gooey_ui::Config {
@@ -112,54 +121,31 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                     children: vec![],
                 },
             },
-            Element::Button {
-                text: ui_text_for_button(
-                    "STARS:",
-                    asset_server,
-                    options.colorscheme.bg_color(),
-                    options.stars.to_string().as_str(),
-                ),
-                config: gooey_ui::Config {
-                    id: "BTN-STARS",
-                    style: main_options_style(),
-                    children: vec![],
-                },
-            },
-            Element::Button {
-                text: ui_text_for_button(
-                    "DUST:",
-                    asset_server,
-                    options.colorscheme.bg_color(),
-                    options.dust.to_string().as_str(),
-                ),
-                config: gooey_ui::Config {
-                    id: "BTN-DUST",
-                    style: main_options_style(),
-                    children: vec![],
-                },
-            },
-            Element::Button {
-                text: ui_text_for_button(
-                    "NEBULAE:",
-                    asset_server,
-                    options.colorscheme.bg_color(),
-                    options.nebulae.to_string().as_str(),
-                ),
-                config: gooey_ui::Config {
-                    id: "BTN-NEBULAE",
-                    style: main_options_style(),
-                    children: vec![],
-                },
+        ]
+        .into_iter()
+        .chain(layers.iter().map(|layer| Element::Button {
+            text: ui_text_for_button(
+                &format!("{}:", layer.label()),
+                asset_server,
+                palette.bg_color(),
+                (layer.enabled)(options).to_string().as_str(),
+            ),
+            config: gooey_ui::Config {
+                id: layer.button,
+                style: main_options_style(),
+                children: vec![],
             },
+        }))
+        .chain([
             Element::Button {
                 text: ui_text_for_button(
-                    "PLANETS:",
+                    "PARALLAX:",
                     asset_server,
-                    options.colorscheme.bg_color(),
-                    options.planets.to_string().as_str(),
+                    palette.bg_color(),
+                    options.parallax.to_string().as_str(),
                 ),
                 config: gooey_ui::Config {
-                    id: "BTN-PLANETS",
+                    id: "BTN-PARALLAX",
                     style: main_options_style(),
                     children: vec![],
                 },
@@ -168,7 +154,7 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                 text: ui_text_for_button(
                     "TILE:",
                     asset_server,
-                    options.colorscheme.bg_color(),
+                    palette.bg_color(),
                     options.tile.to_string().as_str(),
                 ),
                 config: gooey_ui::Config {
@@ -181,7 +167,7 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                 text: ui_text_for_button(
                     "DARKEN:",
                     asset_server,
-                    options.colorscheme.bg_color(),
+                    palette.bg_color(),
                     options.darken.to_string().as_str(),
                 ),
                 config: gooey_ui::Config {
@@ -194,7 +180,7 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                 text: ui_text_for_button(
                     "TRANSPARENCY:",
                     asset_server,
-                    options.colorscheme.bg_color(),
+                    palette.bg_color(),
                     options.transparency.to_string().as_str(),
                 ),
                 config: gooey_ui::Config {
@@ -207,7 +193,7 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                 text: ui_text_for_button(
                     "ANIMATION:",
                     asset_server,
-                    options.colorscheme.bg_color(),
+                    palette.bg_color(),
                     options.animate.to_string().as_str(),
                 ),
                 config: gooey_ui::Config {
@@ -216,7 +202,8 @@ fn menu(asset_server: &AssetServer, options: &config::Options) -> gooey_ui::Elem
                     children: vec![],
                 },
             },
-        ],
+        ])
+        .collect(),
     })
 }
 
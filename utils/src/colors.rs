@@ -38,10 +38,14 @@ fn hex_to_srgb(hex: &str) -> Result<(f32, f32, f32), String> {
     // Remove the leading '#' if present
     let hex = hex.trim_start_matches('#');
 
-    // Ensure the hex string is exactly 6 characters
+    // Ensure the hex string is exactly 6 hex digits, so the byte slices below
+    // fall on character boundaries
     if hex.len() != 6 {
         return Err(format!("Invalid hex color code length: {}", hex.len()));
     }
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex color code: {hex}"));
+    }
 
     // Parse the hex string into RGB components
     let r =
@@ -60,13 +64,18 @@ fn hex_to_srgb(hex: &str) -> Result<(f32, f32, f32), String> {
 }
 
 pub fn color_gradiant(hex_codes: &[&str], width: usize) -> Image {
+    let colors: Vec<Color> = hex_codes.iter().map(|hex| hex_to_color(hex)).collect();
+    gradient(&colors, width)
+}
+
+pub fn gradient(colors: &[Color], width: usize) -> Image {
     let mut gradient = Vec::with_capacity(width);
-    let num_sections = hex_codes.len() - 1;
+    let num_sections = colors.len() - 1;
     let section_width = width / num_sections;
 
     for i in 0..num_sections {
-        let start_color = hex_to_color(hex_codes[i]);
-        let end_color = hex_to_color(hex_codes[i + 1]);
+        let start_color = colors[i];
+        let end_color = colors[i + 1];
 
         for j in 0..section_width {
             let t = j as f32 / section_width as f32;
@@ -76,7 +85,7 @@ pub fn color_gradiant(hex_codes: &[&str], width: usize) -> Image {
     }
 
     while gradient.len() < width {
-        gradient.push(hex_to_color(hex_codes.last().unwrap()));
+        gradient.push(*colors.last().unwrap());
     }
 
     let width = gradient.len();
@@ -111,3 +120,268 @@ pub fn color_gradiant(hex_codes: &[&str], width: usize) -> Image {
 
     image
 }
+
+/// Text palette formats exported by Lospec and most pixel art editors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+    /// One `rrggbb` per line.
+    Hex,
+    /// GIMP `.gpl`.
+    Gimp,
+    /// JASC `.pal`, as written by Paint Shop Pro and Aseprite.
+    Jasc,
+    /// Paint.NET `.txt`, one `aarrggbb` per line after a `; paint.net` header
+    /// comment.
+    PaintNet,
+}
+
+impl PaletteFormat {
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "hex" => Some(PaletteFormat::Hex),
+            "gpl" => Some(PaletteFormat::Gimp),
+            "pal" => Some(PaletteFormat::Jasc),
+            "txt" => Some(PaletteFormat::PaintNet),
+            _ => None,
+        }
+    }
+
+    pub fn parse(self, text: &str) -> Result<Vec<Color>, PaletteError> {
+        match self {
+            PaletteFormat::Hex => parse_hex(text),
+            PaletteFormat::Gimp => parse_gpl(text),
+            PaletteFormat::Jasc => parse_jasc(text),
+            PaletteFormat::PaintNet => parse_paint_net(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteError {
+    /// 1-based line the error was found on.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+/// Non-empty lines with their 1-based line number.
+fn palette_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+fn palette_error(line: usize, message: impl Into<String>) -> PaletteError {
+    PaletteError {
+        line,
+        message: message.into(),
+    }
+}
+
+fn parse_hex(text: &str) -> Result<Vec<Color>, PaletteError> {
+    palette_lines(text)
+        .map(|(line, hex)| {
+            let (r, g, b) = hex_to_srgb(hex).map_err(|err| palette_error(line, err))?;
+            Ok(Color::srgb(r, g, b))
+        })
+        .collect()
+}
+
+fn parse_gpl(text: &str) -> Result<Vec<Color>, PaletteError> {
+    let mut lines = palette_lines(text);
+    match lines.next() {
+        Some((_, "GIMP Palette")) => {}
+        Some((line, _)) => return Err(palette_error(line, "missing \"GIMP Palette\" header")),
+        None => return Err(palette_error(1, "empty palette")),
+    }
+
+    lines
+        .filter(|(_, line)| {
+            !line.starts_with('#') && !line.starts_with("Name:") && !line.starts_with("Columns:")
+        })
+        // anything after the three channels is the color's name
+        .map(|(line, rgb)| parse_rgb(line, rgb.split_whitespace().take(3)))
+        .collect()
+}
+
+fn parse_jasc(text: &str) -> Result<Vec<Color>, PaletteError> {
+    let mut lines = palette_lines(text);
+    match lines.next() {
+        Some((_, "JASC-PAL")) => {}
+        Some((line, _)) => return Err(palette_error(line, "missing \"JASC-PAL\" header")),
+        None => return Err(palette_error(1, "empty palette")),
+    }
+    // version, always 0100
+    lines.next();
+    let (count_line, count) = lines
+        .next()
+        .ok_or_else(|| palette_error(3, "missing color count"))?;
+    let count: usize = count
+        .parse()
+        .map_err(|err| palette_error(count_line, format!("invalid color count: {err}")))?;
+
+    let colors = lines
+        .map(|(line, rgb)| parse_rgb(line, rgb.split_whitespace()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if colors.len() != count {
+        return Err(palette_error(
+            count_line,
+            format!("expected {count} colors, found {}", colors.len()),
+        ));
+    }
+    Ok(colors)
+}
+
+fn parse_paint_net(text: &str) -> Result<Vec<Color>, PaletteError> {
+    let mut lines = palette_lines(text).peekable();
+    match lines.peek() {
+        Some((_, header))
+            if header
+                .trim_start_matches(';')
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("paint.net") => {}
+        Some((line, _)) => return Err(palette_error(*line, "missing \"; paint.net\" header")),
+        None => return Err(palette_error(1, "empty palette")),
+    }
+
+    lines
+        .filter(|(_, line)| !line.starts_with(';'))
+        .map(|(line, argb)| {
+            // the palette is opaque, so the alpha byte is dropped
+            let rgb = argb
+                .get(2..)
+                .filter(|_| argb.len() == 8)
+                .ok_or_else(|| palette_error(line, format!("expected aarrggbb, found {argb}")))?;
+            let (r, g, b) = hex_to_srgb(rgb).map_err(|err| palette_error(line, err))?;
+            Ok(Color::srgb(r, g, b))
+        })
+        .collect()
+}
+
+fn parse_rgb<'a>(
+    line: usize,
+    mut channels: impl Iterator<Item = &'a str>,
+) -> Result<Color, PaletteError> {
+    let mut rgb = [0u8; 3];
+    for channel in &mut rgb {
+        let value = channels
+            .next()
+            .ok_or_else(|| palette_error(line, "expected three color channels"))?;
+        *channel = value
+            .parse()
+            .map_err(|err| palette_error(line, format!("invalid channel {value}: {err}")))?;
+    }
+    if channels.next().is_some() {
+        return Err(palette_error(line, "expected three color channels"));
+    }
+    Ok(Color::srgb_u8(rgb[0], rgb[1], rgb[2]))
+}
+
+/// Resamples `colors` to `count` evenly spaced colors, keeping the first and
+/// last and blending neighbours in between.
+pub fn resample(colors: &[Color], count: usize) -> Vec<Color> {
+    match (colors.len(), count) {
+        (_, 0) | (0, _) => Vec::new(),
+        (1, _) | (_, 1) => vec![colors[0]; count],
+        (len, _) if len == count => colors.to_vec(),
+        (len, _) => (0..count)
+            .map(|i| {
+                let t = i as f32 * (len - 1) as f32 / (count - 1) as f32;
+                let index = (t.floor() as usize).min(len - 2);
+                colors[index].mix(&colors[index + 1], t - index as f32)
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::color::ColorToPacked;
+
+    fn srgb_u8(colors: &[Color]) -> Vec<[u8; 3]> {
+        colors
+            .iter()
+            .map(|color| {
+                let [r, g, b, _] = color.to_srgba().to_u8_array();
+                [r, g, b]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hex_rejects_non_hex_digits() {
+        assert!(hex_to_srgb("ff00ff").is_ok());
+        assert!(hex_to_srgb("#gg0000").is_err());
+        // six bytes, but slicing at 2 would split the 'é'
+        assert!(hex_to_srgb("aé000").is_err());
+        assert!(hex_to_srgb("ff00f").is_err());
+    }
+
+    #[test]
+    fn parses_hex() {
+        let colors = parse_hex("ff0000\n\n  00ff00  \n0000ff\n").unwrap();
+        assert_eq!(srgb_u8(&colors), [[255, 0, 0], [0, 255, 0], [0, 0, 255]]);
+
+        let err = parse_hex("ff0000\nnope\n").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn parses_gpl() {
+        let text = "GIMP Palette\nName: test\nColumns: 2\n# comment\n\n255   0   0\tRed\n  0 128 255 Sky blue\n";
+        let colors = parse_gpl(text).unwrap();
+        assert_eq!(srgb_u8(&colors), [[255, 0, 0], [0, 128, 255]]);
+
+        assert_eq!(parse_gpl("255 0 0\n").unwrap_err().line, 1);
+        assert_eq!(parse_gpl("GIMP Palette\n255 0\n").unwrap_err().line, 2);
+        assert_eq!(parse_gpl("GIMP Palette\n256 0 0\n").unwrap_err().line, 2);
+    }
+
+    #[test]
+    fn parses_jasc() {
+        let colors = parse_jasc("JASC-PAL\n0100\n2\n255 0 0\n\n0 0 255\n").unwrap();
+        assert_eq!(srgb_u8(&colors), [[255, 0, 0], [0, 0, 255]]);
+
+        assert_eq!(
+            parse_jasc("JASC-PAL\n0100\n3\n255 0 0\n").unwrap_err().line,
+            3
+        );
+        assert_eq!(
+            parse_jasc("JASC-PAL\n0100\n1\n255 0 0 0\n")
+                .unwrap_err()
+                .line,
+            4
+        );
+        assert_eq!(parse_jasc("GIMP Palette\n").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn parses_paint_net() {
+        let text = "; paint.net Palette File\n; Colors: 2\n\nFFFF0000\nff00ff00\n";
+        let colors = parse_paint_net(text).unwrap();
+        assert_eq!(srgb_u8(&colors), [[255, 0, 0], [0, 255, 0]]);
+
+        assert_eq!(
+            parse_paint_net("; paint.net\nFF0000\n").unwrap_err().line,
+            2
+        );
+        assert_eq!(
+            parse_paint_net("; paint.net\nFFé0000\n").unwrap_err().line,
+            2
+        );
+        // any other .txt is not a palette
+        assert_eq!(parse_paint_net("FFFF0000\n").unwrap_err().line, 1);
+        assert_eq!(parse_paint_net("; notes\nFFFF0000\n").unwrap_err().line, 1);
+    }
+}
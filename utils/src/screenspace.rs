@@ -9,13 +9,26 @@
  \:::\   \:::\   \/____/  \:::\    \      \/____/  \/____|:::::\/:::/    /  \:::\   \:::\   \/____/  \:::\   \:::\   \/____/  \/____/ |::| /:::/    /          \:::\   \:::\   \/____/  \/_____/\:::\/:::/    /  \/____/ \:::\/:::/    /  \:::\    \      \/____/  \:::\   \:::\   \/____/
 *********************************************************************/
 
+use std::str::FromStr;
+
 use serde::Deserialize;
 
-#[derive(Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub enum CommonResolution {
     FourK16x9,
 }
 
+impl FromStr for CommonResolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "FourK16x9" => Ok(CommonResolution::FourK16x9),
+            _ => Err(format!("unknown resolution: {s}")),
+        }
+    }
+}
+
 impl CommonResolution {
     pub const fn space(self) -> Space {
         let (width, height) = match self {